@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub fetched: DateTime<Utc>,
+    pub body: String,
+}
+
+/// Meta responses stored in the user's cache directory, one file per URL
+#[derive(Debug)]
+pub struct MetaCache {
+    dir: PathBuf,
+}
+
+impl MetaCache {
+    pub fn new() -> Option<MetaCache> {
+        let mut dir = dirs::cache_dir()?;
+        dir.push("quilt-installer");
+        dir.push("meta");
+        Some(MetaCache { dir })
+    }
+
+    pub fn load(&self, url: &str) -> Option<CachedResponse> {
+        let file = std::fs::File::open(self.path(url)).ok()?;
+        serde_json::from_reader(file).ok()
+    }
+
+    /// Writes to a temporary file first, so another installer never reads a half written response
+    pub fn store(&self, url: &str, response: &CachedResponse) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(url);
+        let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+
+        let result = std::fs::File::create(&temp_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| Ok(serde_json::to_writer(file, response)?))
+            .and_then(|()| Ok(std::fs::rename(&temp_path, &path)?));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    // Named by a hash of the URL, so URLs that only differ in punctuation don't share a file
+    fn path(&self, url: &str) -> PathBuf {
+        let hash: String = Sha1::digest(url.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect();
+        self.dir.join(format!("{}.json", hash))
+    }
+}
@@ -0,0 +1,54 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+use crate::error::InstallError;
+
+/// Shared between an installation and whatever can cancel it, like a button or Ctrl-C
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn check(&self) -> Result<(), InstallError> {
+        if self.is_cancelled() {
+            Err(InstallError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn cancelled(&self) {
+        // Created before checking the flag so a cancel in between isn't missed
+        let notified = self.notify.notified();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+
+    /// Runs the future until it completes or the installation is cancelled, whichever comes first
+    pub async fn run<T>(&self, future: impl Future<Output = Result<T, InstallError>>) -> Result<T, InstallError> {
+        tokio::select! {
+            result = future => result,
+            _ = self.cancelled() => Err(InstallError::Cancelled),
+        }
+    }
+}
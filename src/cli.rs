@@ -0,0 +1,430 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::task::JoinHandle;
+
+use crate::cancel::CancelToken;
+use crate::installed::{describe_versions, find_client_installs, find_server_install, uninstall_clients, upgradable_versions, ClientInstall};
+use crate::installer::{client_conflicts, install_client, install_server, modrinth_api_url, server_conflicts, version_manifest_url, ClientInstallation, Conflict, LoaderVersion, MinecraftVersion, ServerInstallation, StartScriptOptions, Upgrade};
+use crate::meta::{offline_notice, MetaSource};
+use crate::progress::{Progress, ProgressEvent};
+use crate::selector::VersionSelector;
+use crate::transform::{configured_transformers, toggle_transformers, ProfileTransformer, Transform};
+use crate::instance::{find_instances_dir, install_instance, instance_conflicts, InstanceInstallation, InstanceTarget};
+use crate::migrate::{find_fabric_migrations, migrate};
+use crate::{ClientArgs, Commands, InstallCommands, InstanceArgs, ListKind, MigrateArgs, ServerArgs, UninstallClientArgs, UninstallCommands};
+
+pub fn run(command: Commands, meta: Arc<dyn MetaSource>) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    let meta = meta.as_ref();
+    let result = runtime.block_on(async {
+        match command {
+            Commands::Install(install) => match install.command {
+                InstallCommands::Client(args) => install_client_command(meta, args).await,
+                InstallCommands::Server(args) => install_server_command(meta, args).await,
+                InstallCommands::Instance(args) => install_instance_command(meta, args).await,
+            },
+            Commands::Uninstall(uninstall) => match uninstall.command {
+                UninstallCommands::Client(args) => uninstall_client_command(args),
+            },
+            Commands::Migrate(args) => migrate_command(meta, args).await,
+            Commands::Status { dir } => status_command(meta, dir).await,
+            Commands::List { kind, snapshots, betas, json } => list_command(meta, kind, snapshots, betas, json).await,
+        }
+    });
+
+    if let Some(since) = meta.offline_since() {
+        eprintln!("{}", offline_notice(since));
+    }
+
+    result
+}
+
+async fn install_client_command(meta: &dyn MetaSource, args: ClientArgs) -> Result<()> {
+    let minecraft_version = resolve_minecraft_version(meta, &args.minecraft).await?;
+    let loader_version = resolve_loader_version(meta, &args.loader).await?;
+
+    let mut transformers = enabled_transformers(&args.enabled_transformers, &args.disabled_transformers)?;
+    if !args.jvm_args.is_empty() {
+        transformers.push(ProfileTransformer::new(Transform::JvmArgs { args: args.jvm_args }));
+    }
+
+    let upgrade = if args.upgrade {
+        let from = upgradable_versions(&args.dir, &minecraft_version, &loader_version)?;
+        if from.is_empty() {
+            println!("No older Quilt versions for Minecraft {} to upgrade", minecraft_version);
+        }
+        Some(Upgrade { from, remove_old: args.remove_old, copy_profiles: false, separate_game_dirs: false })
+    } else {
+        None
+    };
+
+    let installation = ClientInstallation {
+        minecraft_version: minecraft_version.clone(),
+        loader_version: loader_version.clone(),
+        install_location: args.dir,
+        generate_profile: !args.no_profile,
+        download_libraries: args.download_libraries,
+        transformers,
+        suffix: None,
+        upgrade,
+    };
+    if !args.force {
+        refuse_conflicts(&client_conflicts(&installation))?;
+    }
+
+    let (sender, receiver) = unbounded_channel();
+    let printer = tokio::spawn(print_progress(receiver));
+    let (cancel, ctrl_c) = cancel_on_ctrl_c();
+    let result = install_client(meta, installation, &Progress::new(sender), &cancel).await;
+    ctrl_c.abort();
+    let _ = printer.await;
+    result?;
+
+    println!("Installed Quilt Loader {} for Minecraft {}", loader_version, minecraft_version);
+    Ok(())
+}
+
+async fn install_server_command(meta: &dyn MetaSource, args: ServerArgs) -> Result<()> {
+    let minecraft_version = resolve_minecraft_version(meta, &args.minecraft).await?;
+    let loader_version = resolve_loader_version(meta, &args.loader).await?;
+
+    let transformers = enabled_transformers(&args.enabled_transformers, &args.disabled_transformers)?;
+    let installation = ServerInstallation {
+        minecraft_version: minecraft_version.clone(),
+        loader_version: loader_version.clone(),
+        install_location: args.dir.clone(),
+        download_jar: args.download_server_jar,
+        generate_script: args.generate_script,
+        script_options: StartScriptOptions {
+            java: args.script.java,
+            min_memory: args.script.min_memory,
+            max_memory: args.script.max_memory,
+            jvm_args: args.script.jvm_args,
+            nogui: !args.script.gui,
+            systemd_user: args.script.systemd_user,
+        },
+        transformers,
+        version_manifest_url: version_manifest_url(args.version_manifest_url)?,
+    };
+    if !args.force {
+        refuse_conflicts(&server_conflicts(&installation))?;
+    }
+
+    let created_dir = args.create_dir && !args.dir.exists();
+    if created_dir {
+        std::fs::create_dir_all(&args.dir)?;
+    }
+
+    let (sender, receiver) = unbounded_channel();
+    let printer = tokio::spawn(print_progress(receiver));
+    let (cancel, ctrl_c) = cancel_on_ctrl_c();
+    let result = install_server(meta, installation, &Progress::new(sender), &cancel).await;
+    ctrl_c.abort();
+    let _ = printer.await;
+
+    // The installer cleans up after itself, so this only removes the directory if it's empty again
+    if result.is_err() && created_dir {
+        let _ = std::fs::remove_dir(&args.dir);
+    }
+    result?;
+
+    println!("Installed Quilt Loader {} server for Minecraft {}", loader_version, minecraft_version);
+    Ok(())
+}
+
+async fn install_instance_command(meta: &dyn MetaSource, args: InstanceArgs) -> Result<()> {
+    let target = match (args.zip, args.instances_dir) {
+        (Some(zip), _) => InstanceTarget::Zip(zip),
+        (None, Some(instances_dir)) => InstanceTarget::Directory(instances_dir),
+        (None, None) => InstanceTarget::Directory(find_instances_dir()
+            .ok_or_else(|| anyhow!("Couldn't find Prism Launcher's instances directory. Pass it with --instances-dir, or use --zip to import the instance yourself"))?),
+    };
+
+    let minecraft_version = resolve_minecraft_version(meta, &args.minecraft).await?;
+    let loader_version = resolve_loader_version(meta, &args.loader).await?;
+
+    let installation = InstanceInstallation {
+        name: args.name.unwrap_or_else(|| InstanceInstallation::default_name(&minecraft_version)),
+        minecraft_version: minecraft_version.clone(),
+        loader_version: loader_version.clone(),
+        target,
+        transformers: enabled_transformers(&args.enabled_transformers, &args.disabled_transformers)?,
+    };
+    if !args.force {
+        refuse_conflicts(&instance_conflicts(&installation))?;
+    }
+
+    let path = installation.path();
+    let is_zip = matches!(installation.target, InstanceTarget::Zip(_));
+
+    let (sender, receiver) = unbounded_channel();
+    let printer = tokio::spawn(print_progress(receiver));
+    let (cancel, ctrl_c) = cancel_on_ctrl_c();
+    let result = install_instance(meta, installation, &Progress::new(sender), &cancel).await;
+    ctrl_c.abort();
+    let _ = printer.await;
+    result?;
+
+    if is_zip {
+        println!("Wrote {}, import it in the launcher to add Quilt Loader {} for Minecraft {}", path.display(), loader_version, minecraft_version);
+    } else {
+        println!("Created an instance with Quilt Loader {} for Minecraft {} in {}", loader_version, minecraft_version, path.display());
+    }
+    Ok(())
+}
+
+fn uninstall_client_command(args: UninstallClientArgs) -> Result<()> {
+    let installs = find_client_installs(&args.dir)?;
+
+    if !args.all && args.versions.is_empty() {
+        if installs.is_empty() {
+            println!("No Quilt versions are installed in {}", args.dir.display());
+        } else {
+            installs.iter().for_each(print_client_install);
+            println!("Pass --version <VERSION> or --all to choose what to remove");
+        }
+        return Ok(());
+    }
+
+    let selected: Vec<ClientInstall> = if args.all {
+        installs
+    } else {
+        args.versions.iter()
+            .map(|version| installs.iter().find(|install| &install.version_id == version).cloned()
+                .ok_or_else(|| anyhow!("Quilt version `{}` isn't installed in {}", version, args.dir.display())))
+            .collect::<Result<_>>()?
+    };
+
+    if !args.yes {
+        println!("This removes:");
+        selected.iter().for_each(print_client_install);
+        if !confirm("Remove these versions and their launcher profiles?")? {
+            return Err(anyhow!("Nothing was removed, pass --yes to remove without asking"));
+        }
+    }
+
+    uninstall_clients(&args.dir, &selected)?;
+    for install in &selected {
+        println!("Removed {}", install.version_id);
+    }
+
+    Ok(())
+}
+
+async fn migrate_command(meta: &dyn MetaSource, args: MigrateArgs) -> Result<()> {
+    let migrations = find_fabric_migrations(&args.dir)?;
+    if migrations.is_empty() {
+        println!("No Fabric installations found in {}", args.dir.display());
+        return Ok(());
+    }
+
+    let loader_version = resolve_loader_version(meta, &args.loader).await?;
+    let minecraft_versions = meta.minecraft_versions().await?;
+    let transformers = enabled_transformers(&args.enabled_transformers, &args.disabled_transformers)?;
+    let modrinth_api_url = modrinth_api_url(args.modrinth_api_url)?;
+
+    let (sender, receiver) = unbounded_channel();
+    let printer = tokio::spawn(print_progress(receiver));
+    let (cancel, ctrl_c) = cancel_on_ctrl_c();
+
+    // One failed Minecraft version shouldn't stop the others from migrating
+    let mut failed = Vec::new();
+    for migration in &migrations {
+        println!("Migrating {} to Quilt Loader {}", migration.fabric_versions.join(", "), loader_version);
+
+        let minecraft_version = match minecraft_versions.iter().find(|version| version.version == migration.minecraft_version) {
+            Some(minecraft_version) => minecraft_version.clone(),
+            None => {
+                eprintln!("Quilt doesn't support Minecraft {}", migration.minecraft_version);
+                failed.push(migration.minecraft_version.clone());
+                continue;
+            },
+        };
+
+        let result: Result<()> = async {
+            let installation = migration.installation(&args.dir, minecraft_version.clone(), loader_version.clone(), transformers.clone(), args.replace_fabric_api);
+            if !args.force {
+                refuse_conflicts(&client_conflicts(&installation))?;
+            }
+            migrate(meta, migration, installation, &modrinth_api_url, &Progress::new(sender.clone()), &cancel).await?;
+            Ok(())
+        }.await;
+
+        match result {
+            Ok(()) if migration.profiles.is_empty() => println!("Installed Quilt for Minecraft {}", minecraft_version),
+            Ok(()) => println!("Copied {} to Quilt", migration.profiles.join(", ")),
+            Err(e) => {
+                eprintln!("Couldn't migrate Minecraft {}: {}", minecraft_version, e);
+                failed.push(minecraft_version.version);
+            },
+        }
+
+        if cancel.is_cancelled() {
+            break;
+        }
+    }
+
+    ctrl_c.abort();
+    drop(sender);
+    let _ = printer.await;
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("Migrating Minecraft {} failed", failed.join(", ")))
+    }
+}
+
+// Anything but an explicit yes, including no terminal to answer on, counts as no
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn print_client_install(install: &ClientInstall) {
+    let mut notes = Vec::new();
+    if install.dir.is_none() {
+        notes.push(String::from("version missing"));
+    }
+    if !install.profiles.is_empty() {
+        notes.push(format!("profiles: {}", install.profiles.join(", ")));
+    }
+
+    if notes.is_empty() {
+        println!("{}", install.version_id);
+    } else {
+        println!("{} ({})", install.version_id, notes.join("; "));
+    }
+}
+
+async fn status_command(meta: &dyn MetaSource, dir: PathBuf) -> Result<()> {
+    let clients = find_client_installs(&dir)?;
+    let server = find_server_install(&dir);
+
+    if clients.is_empty() && server.is_none() {
+        println!("No Quilt installations found in {}", dir.display());
+        return Ok(());
+    }
+
+    let latest = match meta.loader_versions().await {
+        Ok(versions) => VersionSelector::LatestStable.select_loader(&versions).cloned(),
+        Err(e) => {
+            eprintln!("Couldn't check for updates: {}", e);
+            None
+        },
+    };
+
+    for install in &clients {
+        print_client_install(install);
+        println!("  {}", describe_versions(&install.loader_version, &install.minecraft_version, latest.as_ref()));
+    }
+
+    if let Some(server) = server {
+        println!("Server in {}", server.dir.display());
+        println!("  {}", describe_versions(&server.loader_version, &server.minecraft_version, latest.as_ref()));
+        match server.server_jar {
+            Some(jar) => println!("  Starts {}", jar),
+            None => println!("  No vanilla server jar"),
+        }
+    }
+
+    Ok(())
+}
+
+fn refuse_conflicts(conflicts: &[Conflict]) -> Result<()> {
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+
+    let list: Vec<String> = conflicts.iter().map(|conflict| format!("  {}", conflict)).collect();
+    Err(anyhow!("An existing installation would be replaced:\n{}\nRun again with --force to replace it", list.join("\n")))
+}
+
+fn cancel_on_ctrl_c() -> (CancelToken, JoinHandle<()>) {
+    let cancel = CancelToken::new();
+    let handler = tokio::spawn({
+        let cancel = cancel.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                eprintln!("Cancelling installation...");
+                cancel.cancel();
+            }
+        }
+    });
+
+    (cancel, handler)
+}
+
+async fn print_progress(mut receiver: UnboundedReceiver<ProgressEvent>) {
+    let mut last_step = None;
+    while let Some(event) = receiver.recv().await {
+        if last_step.as_ref() != Some(&event.step) {
+            println!("[{:>3}%] {}", (event.progress * 100.0) as u32, event.step);
+            last_step = Some(event.step);
+        }
+    }
+}
+
+fn enabled_transformers(enabled: &[String], disabled: &[String]) -> Result<Vec<ProfileTransformer>> {
+    let mut transformers = configured_transformers()?;
+    toggle_transformers(&mut transformers, enabled, disabled)?;
+    Ok(transformers)
+}
+
+async fn list_command(meta: &dyn MetaSource, kind: ListKind, snapshots: bool, betas: bool, json: bool) -> Result<()> {
+    let versions: Vec<String> = match kind {
+        ListKind::Game => {
+            let versions: Vec<MinecraftVersion> = meta.minecraft_versions().await?
+                .into_iter()
+                .filter(|v| snapshots || v.stable)
+                .collect();
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&versions)?);
+                return Ok(());
+            }
+
+            versions.iter().map(|v| v.version.clone()).collect()
+        },
+        ListKind::Loader => {
+            let versions: Vec<LoaderVersion> = meta.loader_versions().await?
+                .into_iter()
+                .filter(|v| betas || !v.is_beta())
+                .collect();
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&versions)?);
+                return Ok(());
+            }
+
+            versions.iter().map(|v| v.version.clone()).collect()
+        },
+    };
+
+    for version in versions {
+        println!("{}", version);
+    }
+
+    Ok(())
+}
+
+async fn resolve_minecraft_version(meta: &dyn MetaSource, selector: &VersionSelector) -> Result<MinecraftVersion> {
+    selector.select_minecraft(&meta.minecraft_versions().await?)
+        .cloned()
+        .ok_or_else(|| anyhow!("No Minecraft version matches `{}`", selector))
+}
+
+async fn resolve_loader_version(meta: &dyn MetaSource, selector: &VersionSelector) -> Result<LoaderVersion> {
+    selector.select_loader(&meta.loader_versions().await?)
+        .cloned()
+        .ok_or_else(|| anyhow!("No Quilt Loader version matches `{}`", selector))
+}
@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use crate::transform::ProfileTransformer;
+
+/// Settings read from `config.json` in the user's config directory, e.g. `~/.config/quilt-installer/config.json`
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    pub meta_url: Option<String>,
+    pub version_manifest_url: Option<String>,
+    pub modrinth_api_url: Option<String>,
+    pub transformers: Option<Vec<ProfileTransformer>>,
+}
+
+impl Config {
+    pub fn load() -> Result<Config> {
+        let path = match config_path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Config::default()),
+        };
+
+        let file = std::fs::File::open(&path)?;
+        serde_json::from_reader(file).with_context(|| anyhow!("Invalid config file {:?}", path))
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    let mut path = dirs::config_dir()?;
+    path.push("quilt-installer");
+    path.push("config.json");
+    Some(path)
+}
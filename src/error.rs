@@ -0,0 +1,99 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+/// Everything that can go wrong while installing. The messages are shown to users as-is,
+/// so they should say what to do about the problem.
+#[derive(Debug, Error)]
+pub enum InstallError {
+    #[error("Couldn't download {url}. Check your internet connection and try again.")]
+    Network {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("{0} doesn't exist on Quilt meta.")]
+    MetaNotFound(String),
+    #[error("Quilt meta returned something unexpected for {what}. Try again later, and report it to Quilt if it keeps happening.")]
+    InvalidMetaResponse {
+        what: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Quilt Loader {loader} isn't available for Minecraft {minecraft}. Pick a different combination of versions.")]
+    UnsupportedVersions {
+        minecraft: String,
+        loader: String,
+    },
+    #[error("The directory {0:?} doesn't exist. Pick an existing directory, or start the Minecraft launcher once to create it.")]
+    DirectoryNotFound(PathBuf),
+    #[error("There's no launcher_profiles.json in {0:?}. Start the Minecraft launcher once, or install without creating a profile.")]
+    LauncherProfilesNotFound(PathBuf),
+    #[error("{path:?} is damaged and can't be read. Start the Minecraft launcher to repair it, or install without creating a profile.")]
+    MalformedLauncherProfiles {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Permission denied for {path:?}. Close the Minecraft launcher, or pick a directory you're allowed to write to.")]
+    PermissionDenied {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Couldn't access {path:?}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("The library {0} in the Quilt profile can't be installed. Report this to Quilt.")]
+    InvalidLibrary(String),
+    #[error("Mojang doesn't provide a server jar for Minecraft {0}. Install without downloading the server jar and add it yourself.")]
+    ServerJarUnavailable(String),
+    #[error("Quilted Fabric API isn't available for Minecraft {0} yet. Migrate without replacing Fabric API.")]
+    QuiltedFabricApiUnavailable(String),
+    #[error("The file downloaded from {url} is damaged, its {algorithm} checksum should be {expected} but is {actual}. Try installing again.")]
+    ChecksumMismatch {
+        url: String,
+        algorithm: &'static str,
+        expected: String,
+        actual: String,
+    },
+    #[error("{0} has no published .sha512 or .sha1 checksum, so it can't be verified.")]
+    MissingChecksum(String),
+    #[error("Couldn't write the server launcher jar.")]
+    Jar(#[from] zip::result::ZipError),
+    #[error("Couldn't write the instance zip.")]
+    InstanceZip(#[source] zip::result::ZipError),
+    #[error("The installation was cancelled.")]
+    Cancelled,
+}
+
+impl InstallError {
+    /// The message along with every underlying error, for bug reports
+    pub fn details(&self) -> String {
+        let mut details = format!("{}", self);
+        let mut source = std::error::Error::source(self);
+        while let Some(error) = source {
+            details.push_str(&format!("\nCaused by: {}", error));
+            source = error.source();
+        }
+        details
+    }
+}
+
+pub trait IoResultExt<T> {
+    /// Attaches the path an IO operation failed on
+    fn with_path(self, path: &Path) -> Result<T, InstallError>;
+}
+
+impl<T> IoResultExt<T> for std::io::Result<T> {
+    fn with_path(self, path: &Path) -> Result<T, InstallError> {
+        self.map_err(|source| match source.kind() {
+            ErrorKind::PermissionDenied => InstallError::PermissionDenied { path: path.to_owned(), source },
+            _ => InstallError::Io { path: path.to_owned(), source },
+        })
+    }
+}
@@ -1,25 +1,37 @@
 use std::borrow::Cow;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use anyhow::{Result, Error, anyhow};
+use chrono::{DateTime, Utc};
 use iced::theme::palette::{Background, Primary, Secondary, Success, Danger};
-use iced::theme::{Container, Palette, Custom};
-use iced::{Settings, Application, executor, Command, Length, Element, Theme, color, Color, Font};
+use iced::theme::{Button, Container, Palette, Custom};
+use iced::{Settings, Application, executor, clipboard, subscription, Command, Subscription, Length, Element, Theme, color, Color, Font};
 use iced::alignment::{Alignment, Horizontal};
-use iced::widget::{pick_list, column, text, checkbox, row, horizontal_rule, text_input, button, progress_bar, vertical_space, container, horizontal_space, scrollable};
+use iced::widget::{pick_list, column, text, checkbox, row, horizontal_rule, text_input, button, progress_bar, vertical_space, container, horizontal_space, scrollable, Column};
 use iced::window::Icon;
 use image::ImageFormat;
 use rfd::FileDialog;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 
-use crate::installer::{MinecraftVersion, fetch_minecraft_versions, LoaderVersion, fetch_loader_versions, install_client, ClientInstallation};
+use crate::cancel::CancelToken;
+use crate::error::{InstallError, IoResultExt};
+use crate::installed::{describe_versions, find_client_installs, find_server_install, uninstall_clients, upgradable_versions, ClientInstall, ServerInstall};
+use crate::installer::{MinecraftVersion, LoaderVersion, install_client, install_server, ClientInstallation, ServerInstallation, StartScriptOptions, Installation, Conflict, client_conflicts, server_conflicts, unused_suffix, version_manifest_url, modrinth_api_url, Upgrade, MOJANG_VERSION_MANIFEST, MODRINTH_API};
+use crate::log::{log, log_path, warning};
+use crate::instance::{find_instances_dir, install_instance, instance_conflicts, InstanceInstallation, InstanceTarget};
+use crate::meta::{offline_notice, MetaSource};
+use crate::migrate::{find_fabric_migrations, migrate, FabricMigration};
+use crate::progress::{Progress, ProgressEvent};
+use crate::selector::VersionSelector;
+use crate::transform::{configured_transformers, default_transformers, ProfileTransformer};
 use crate::{Args, FONT_MEDIUM, ICON, FONT_REGULAR, theme, FONT_SEMIBOLD};
 
 const POPPINS_REGULAR_FONT: Font = Font::External { name: "Poppins Regular", bytes: FONT_REGULAR };
 const POPPINS_SEMIBOLD_FONT: Font = Font::External { name: "Poppins Semi-Bold", bytes: FONT_SEMIBOLD };
 
-pub fn run() -> iced::Result {
-    let mut settings = Settings::default();
-    // settings.flags = args;
+pub fn run(meta: Arc<dyn MetaSource>) -> iced::Result {
+    let mut settings = Settings::with_flags(meta);
     settings.default_font = Some(FONT_MEDIUM);
     settings.window.size = (400, 500);
     settings.window.icon = Some(Icon::from_file_data(ICON, Some(ImageFormat::Png)).unwrap());
@@ -31,8 +43,11 @@ pub fn run() -> iced::Result {
 
 #[derive(Debug)]
 struct Installer {
+    meta: Arc<dyn MetaSource>,
     page: Page,
 
+    offline_since: Option<DateTime<Utc>>,
+
     have_minecraft_versions_loaded: Option<bool>,
     minecraft_versions: Vec<MinecraftVersion>,
     selected_minecraft_version: Option<MinecraftVersion>,
@@ -45,8 +60,46 @@ struct Installer {
 
     directory: PathBuf,
     create_profile: bool,
+    download_libraries: bool,
+    upgrade_existing: bool,
+    remove_old_versions: bool,
+
+    installation: Installation,
+    server_directory: PathBuf,
+    download_server_jar: bool,
+    generate_script: bool,
+    instances_directory: PathBuf,
+    export_zip: bool,
+    transformers: Vec<ProfileTransformer>,
+    transformers_error: Option<String>,
 
     is_installing: bool,
+    install_attempt: u64,
+    install_cancel: CancelToken,
+    install_progress: Option<ProgressEvent>,
+    progress_receiver: Arc<Mutex<Option<UnboundedReceiver<ProgressEvent>>>>,
+    install_error: Option<Arc<InstallError>>,
+    show_error_details: bool,
+
+    conflicts: Vec<Conflict>,
+    pending_install: Option<PendingInstall>,
+
+    client_installs: Vec<(ClientInstall, bool)>,
+    server_install: Option<ServerInstall>,
+    uninstall_status: Option<String>,
+
+    fabric_migrations: Vec<(FabricMigration, bool)>,
+    replace_fabric_api: bool,
+    migrate_status: Option<String>,
+}
+
+#[derive(Debug)]
+enum PendingInstall {
+    Client(ClientInstallation),
+    Server(ServerInstallation),
+    Instance(InstanceInstallation),
+    /// The migrations to run, and the Minecraft versions Quilt doesn't support
+    Migrate(Vec<(FabricMigration, ClientInstallation)>, Vec<String>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -54,13 +107,32 @@ enum Page {
     Main,
     ClientInstallLoading,
     ClientInstall,
-    ServerDownload
+    ServerDownload,
+    InstanceInstall,
+    ConfirmOverwrite,
+    InstallFailed,
+    Uninstall,
+    ConfirmUninstall,
+    Installed,
+    Migrate,
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     UpdatePage(Page),
     EnterClientInstall,
+    EnterServerInstall,
+    EnterInstanceInstall,
+    EnterUninstall,
+    EnterInstalled,
+    EnterMigrate,
+    SelectUninstall(usize, bool),
+    UninstallSelected,
+    ConfirmUninstall,
+    SelectMigration(usize, bool),
+    ReplaceFabricApiCheckmarkChanged(bool),
+    MigrateSelected,
+    MigrationDone(String),
     Retry,
     SetMinecraftVersions(Vec<MinecraftVersion>),
     FailMinecraftVersions,
@@ -68,12 +140,50 @@ enum Message {
     SetLoaderVersions(Vec<LoaderVersion>),
     FailLoaderVersions,
     SelectLoaderVersion(LoaderVersion),
-    DirectoryInputChangeButtonPressed,
+    DirectoryInputChangeButtonPressed(Installation),
     ShowMinecraftSnapshotsCheckmarkChanged(bool),
     ShowLoaderBetasCheckmarkChanged(bool),
     CreateProfileCheckmarkChanged(bool),
+    DownloadLibrariesCheckmarkChanged(bool),
+    UpgradeExistingCheckmarkChanged(bool),
+    RemoveOldVersionsCheckmarkChanged(bool),
+    DownloadServerJarCheckmarkChanged(bool),
+    GenerateScriptCheckmarkChanged(bool),
+    ExportZipCheckmarkChanged(bool),
+    TransformerCheckmarkChanged(usize, bool),
     Install,
+    ReplaceExisting,
+    KeepBoth,
+    CancelOverwrite,
+    CancelInstall,
+    InstallProgress(ProgressEvent),
     InstallationDone,
+    InstallationFailed(Arc<InstallError>),
+    RetryInstall,
+    ToggleErrorDetails,
+    CopyErrorDetails,
+    OpenLogFile,
+}
+
+enum ProgressSubscription {
+    Starting(Arc<Mutex<Option<UnboundedReceiver<ProgressEvent>>>>),
+    Receiving(UnboundedReceiver<ProgressEvent>),
+    Finished,
+}
+
+#[cfg(target_os = "windows")]
+fn open_path(path: &Path) -> std::io::Result<()> {
+    std::process::Command::new("explorer").arg(path).spawn().map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+fn open_path(path: &Path) -> std::io::Result<()> {
+    std::process::Command::new("open").arg(path).spawn().map(|_| ())
+}
+
+#[cfg(target_os = "linux")]
+fn open_path(path: &Path) -> std::io::Result<()> {
+    std::process::Command::new("xdg-open").arg(path).spawn().map(|_| ())
 }
 
 #[cfg(target_os = "windows")]
@@ -99,16 +209,264 @@ fn get_default_client_directory() -> PathBuf {
     dir
 }
 
+// Without Prism Launcher the instance is exported as a zip, which is put in the working directory
+fn get_default_instances_directory() -> PathBuf {
+    find_instances_dir().unwrap_or_else(|| std::env::current_dir().unwrap_or_default())
+}
+
+fn get_default_server_directory() -> PathBuf {
+    let mut dir = std::env::current_dir().unwrap_or_default();
+    dir.push("quilt-server");
+    dir
+}
+
+// A broken config file falls back to the default transformers, and the install pages say why
+fn load_transformers() -> (Vec<ProfileTransformer>, Option<String>) {
+    match configured_transformers() {
+        Ok(transformers) => (transformers, None),
+        Err(e) => {
+            warning!("Couldn't load profile transformers! {}", e);
+            (default_transformers(), Some(format!("Couldn't load the transformers from the config file, using the defaults: {}", e)))
+        },
+    }
+}
+
+async fn migrate_fabric(meta: Arc<dyn MetaSource>, migrations: Vec<(FabricMigration, ClientInstallation)>, mut errors: Vec<String>, sender: UnboundedSender<ProgressEvent>, cancel: CancelToken) -> String {
+    let modrinth_api_url = modrinth_api_url(None).unwrap_or_else(|e| {
+        log!("Couldn't load the Modrinth API URL! {}", e);
+        MODRINTH_API.to_owned()
+    });
+
+    let count = migrations.len();
+    let loader_version = migrations.first().map(|(_, installation)| installation.loader_version.clone());
+    for (migration, installation) in migrations {
+        let minecraft_version = installation.minecraft_version.clone();
+        if let Err(e) = migrate(meta.as_ref(), &migration, installation, &modrinth_api_url, &Progress::new(sender.clone()), &cancel).await {
+            log!("Migrating Minecraft {} failed! {}", minecraft_version, e.details());
+            errors.push(format!("Minecraft {}: {}", minecraft_version, e));
+        }
+
+        if cancel.is_cancelled() {
+            break;
+        }
+    }
+
+    match loader_version {
+        Some(loader_version) if errors.is_empty() => format!("Migrated {} Minecraft version(s) to Quilt Loader {}", count, loader_version),
+        _ => errors.join("\n"),
+    }
+}
+
+fn fetch_versions(meta: &Arc<dyn MetaSource>) -> Command<Message> {
+    let minecraft_meta = meta.clone();
+    let loader_meta = meta.clone();
+
+    Command::batch([
+        Command::perform(async move { minecraft_meta.minecraft_versions().await }, |result| {
+            if result.is_ok() {
+                Message::SetMinecraftVersions(result.unwrap())
+            } else {
+                log!("Couldn't fetch Minecraft versions! {}", result.unwrap_err());
+                Message::FailMinecraftVersions
+            }
+        }),
+        Command::perform(async move { loader_meta.loader_versions().await }, |result| {
+            if result.is_ok() {
+                Message::SetLoaderVersions(result.unwrap())
+            } else {
+                log!("Couldn't fetch Quilt Loader versions! {}", result.unwrap_err());
+                Message::FailLoaderVersions
+            }
+        }),
+    ])
+}
+
+impl Installer {
+    fn scan_client_installs(&mut self) {
+        self.client_installs = match find_client_installs(&self.directory) {
+            Ok(installs) => installs.into_iter().map(|install| (install, false)).collect(),
+            Err(e) => {
+                log!("Couldn't look for installed versions! {}", e.details());
+                self.uninstall_status = Some(e.to_string());
+                vec![]
+            },
+        };
+    }
+
+    fn scan_fabric_migrations(&mut self) {
+        self.fabric_migrations = match find_fabric_migrations(&self.directory) {
+            Ok(migrations) => migrations.into_iter().map(|migration| (migration, true)).collect(),
+            Err(e) => {
+                log!("Couldn't look for Fabric installations! {}", e.details());
+                self.migrate_status = Some(e.to_string());
+                vec![]
+            },
+        };
+    }
+
+    fn install_page(&self) -> Page {
+        match self.installation {
+            Installation::Client => Page::ClientInstall,
+            Installation::Server => Page::ServerDownload,
+            Installation::Instance => Page::InstanceInstall,
+        }
+    }
+
+    fn start_install(&mut self, pending: PendingInstall) -> Command<Message> {
+        let (sender, receiver) = unbounded_channel();
+        self.is_installing = true;
+        self.install_attempt += 1;
+        self.install_cancel = CancelToken::new();
+        self.install_progress = None;
+        self.progress_receiver = Arc::new(Mutex::new(Some(receiver)));
+
+        let meta = self.meta.clone();
+        let cancel = self.install_cancel.clone();
+        let on_result = |result: Result<(), InstallError>| {
+            if result.is_ok() {
+                Message::InstallationDone
+            } else {
+                Message::InstallationFailed(Arc::new(result.unwrap_err()))
+            }
+        };
+
+        match pending {
+            PendingInstall::Client(args) => Command::perform(
+                async move { install_client(meta.as_ref(), args, &Progress::new(sender), &cancel).await },
+                on_result
+            ),
+            PendingInstall::Server(args) => Command::perform(
+                async move {
+                    // The server directory is created here instead of on the page, and removed again if it's still empty after a failure
+                    let created_dir = !args.install_location.exists();
+                    if created_dir {
+                        std::fs::create_dir_all(&args.install_location).with_path(&args.install_location)?;
+                    }
+
+                    let install_location = args.install_location.clone();
+                    let result = install_server(meta.as_ref(), args, &Progress::new(sender), &cancel).await;
+                    if result.is_err() && created_dir {
+                        let _ = std::fs::remove_dir(&install_location);
+                    }
+                    result
+                },
+                on_result
+            ),
+            PendingInstall::Instance(args) => Command::perform(
+                async move { install_instance(meta.as_ref(), args, &Progress::new(sender), &cancel).await },
+                on_result
+            ),
+            PendingInstall::Migrate(migrations, unsupported) => Command::perform(
+                migrate_fabric(meta, migrations, unsupported, sender, cancel),
+                Message::MigrationDone
+            ),
+        }
+    }
+
+    fn version_pickers(&self) -> Element<'_, Message, iced::Renderer<Theme>> {
+        let minecraft_version_label = text("Minecraft Version:").font(POPPINS_SEMIBOLD_FONT).width(Length::Units(185));
+        let minecraft_version_pick_list = pick_list(
+            Cow::from_iter((self.minecraft_versions.iter().filter(|v| self.show_minecraft_snapshots || v.stable)).cloned()),
+            self.selected_minecraft_version.clone(),
+            Message::SelectMinecraftVersion
+        )
+        .width(Length::Units(185));
+
+        let loader_version_label = text("Quilt Loader Version:").font(POPPINS_SEMIBOLD_FONT).width(Length::Units(185));
+        let loader_version_pick_list = pick_list(
+            Cow::from_iter((self.loader_versions.iter().filter(|v| self.show_loader_betas || !v.is_beta())).cloned()),
+            self.selected_loader_version.clone(),
+            Message::SelectLoaderVersion
+        )
+        .width(Length::Units(185));
+
+        row![
+            column![minecraft_version_label, minecraft_version_pick_list].width(Length::Units(185)).spacing(2),
+            column![loader_version_label, loader_version_pick_list].width(Length::Units(185)).spacing(2),
+        ].spacing(10).into()
+    }
+
+    fn directory_mut(&mut self, installation: Installation) -> &mut PathBuf {
+        match installation {
+            Installation::Client => &mut self.directory,
+            Installation::Server => &mut self.server_directory,
+            Installation::Instance => &mut self.instances_directory,
+        }
+    }
+
+    fn directory_chooser<'a>(&self, directory: &'a Path, installation: Installation) -> Element<'a, Message, iced::Renderer<Theme>> {
+        let directory_label = text("Directory:").font(POPPINS_SEMIBOLD_FONT).height(Length::Units(30));
+        let directory_button = button(text("Q").width(Length::Units(30)).horizontal_alignment(Horizontal::Center)).width(Length::Units(30)).on_press(Message::DirectoryInputChangeButtonPressed(installation));
+        let directory_label_row = row![directory_label, horizontal_space(Length::Fill), directory_button].width(Length::Units(380));
+
+        let directory_path = text(directory.display()).width(Length::Units(380)).font(POPPINS_REGULAR_FONT).size(16);
+        column![directory_label_row, directory_path].spacing(2).into()
+    }
+
+    fn transformer_checkboxes(&self) -> Element<'_, Message, iced::Renderer<Theme>> {
+        if self.transformers.is_empty() {
+            return column![].into();
+        }
+
+        let transformers_label = text("Transformers:").width(Length::Units(380)).font(POPPINS_SEMIBOLD_FONT);
+        let mut transformers = column![transformers_label].spacing(5);
+        if let Some(error) = &self.transformers_error {
+            transformers = transformers.push(text(error).width(Length::Units(380)).font(POPPINS_REGULAR_FONT).size(14));
+        }
+        self.transformers.iter().enumerate().fold(transformers, |column, (index, transformer)| {
+            column.push(checkbox(transformer.description(), transformer.enabled, move |enabled| Message::TransformerCheckmarkChanged(index, enabled)).width(Length::Units(380)))
+        }).into()
+    }
+
+    /// Adds the progress of a running installation and the offline notice to an install page
+    fn with_install_status<'a>(&self, mut page: Column<'a, Message, iced::Renderer<Theme>>) -> Element<'a, Message, iced::Renderer<Theme>> {
+        if self.is_installing {
+            let (step, progress) = match &self.install_progress {
+                Some(event) if self.install_cancel.is_cancelled() => (String::from("Cancelling..."), event.progress),
+                Some(event) => (event.step.to_string(), event.progress),
+                None => (String::from("Starting..."), 0.0),
+            };
+
+            let mut cancel = button(text("Cancel").size(14)).style(Button::Secondary);
+            if !self.install_cancel.is_cancelled() {
+                cancel = cancel.on_press(Message::CancelInstall);
+            }
+
+            let progress_row = row![
+                progress_bar(0.0..=1.0, progress).height(Length::Units(10)).width(Length::Units(300)),
+                cancel,
+            ]
+            .align_items(Alignment::Center)
+            .spacing(10);
+
+            page = page
+                .push(progress_row)
+                .push(text(step).font(POPPINS_REGULAR_FONT).size(14));
+        }
+
+        match self.offline_since {
+            Some(since) => {
+                let offline_text = text(offline_notice(since)).font(POPPINS_REGULAR_FONT).size(14);
+                column![offline_text, page].align_items(Alignment::Center).width(Length::Fill).into()
+            },
+            None => page.into(),
+        }
+    }
+}
+
 impl Application for Installer {
     type Executor = executor::Default;
-    type Flags = ();
+    type Flags = Arc<dyn MetaSource>;
     type Message = Message;
     type Theme = Theme;
 
-    fn new(_flags: Self::Flags) -> (Self, Command<Self::Message>) {
+    fn new(flags: Self::Flags) -> (Self, Command<Self::Message>) {
+        let (transformers, transformers_error) = load_transformers();
         (
             Installer {
+                meta: flags.clone(),
                 page: Page::Main,
+                offline_since: None,
                 have_minecraft_versions_loaded: None,
                 minecraft_versions: vec![],
                 selected_minecraft_version: None,
@@ -119,27 +477,34 @@ impl Application for Installer {
                 show_minecraft_snapshots: false,
                 directory: get_default_client_directory(),
                 create_profile: true,
+                download_libraries: false,
+                upgrade_existing: false,
+                remove_old_versions: false,
+                installation: Installation::Client,
+                server_directory: get_default_server_directory(),
+                download_server_jar: true,
+                generate_script: true,
+                instances_directory: get_default_instances_directory(),
+                export_zip: find_instances_dir().is_none(),
+                transformers,
+                transformers_error,
                 is_installing: false,
+                install_attempt: 0,
+                install_cancel: CancelToken::new(),
+                install_progress: None,
+                progress_receiver: Arc::default(),
+                install_error: None,
+                show_error_details: false,
+                conflicts: vec![],
+                pending_install: None,
+                client_installs: vec![],
+                server_install: None,
+                uninstall_status: None,
+                fabric_migrations: vec![],
+                replace_fabric_api: false,
+                migrate_status: None,
             },
-            Command::batch([
-                // TODO - This is a mess; We need a better way to control this
-                Command::perform(fetch_minecraft_versions(), |result| {
-                    if result.is_ok() {
-                        Message::SetMinecraftVersions(result.unwrap())
-                    } else {
-                        println!("Couldn't fetch Minecraft versions! {}", result.unwrap_err());
-                        Message::FailMinecraftVersions
-                    }
-                }),
-                Command::perform(fetch_loader_versions(), |result| {
-                    if result.is_ok() {
-                        Message::SetLoaderVersions(result.unwrap())
-                    } else {
-                        println!("Couldn't fetch Minecraft versions! {}", result.unwrap_err());
-                        Message::FailLoaderVersions
-                    }
-                }),
-            ])
+            fetch_versions(&flags),
         )
     }
 
@@ -151,6 +516,7 @@ impl Application for Installer {
         match message {
             Message::UpdatePage(page) => self.page = page,
             Message::EnterClientInstall => {
+                self.installation = Installation::Client;
                 // TODO - All the code has MC versions in mind, but what about Loader?
                 if self.have_minecraft_versions_loaded.is_some() && self.have_minecraft_versions_loaded.unwrap() {
                     self.page = Page::ClientInstall;
@@ -158,35 +524,34 @@ impl Application for Installer {
                     self.page = Page::ClientInstallLoading
                 }
             }
+            Message::EnterServerInstall => {
+                self.installation = Installation::Server;
+                if self.have_minecraft_versions_loaded.is_some() && self.have_minecraft_versions_loaded.unwrap() {
+                    self.page = Page::ServerDownload;
+                } else {
+                    self.page = Page::ClientInstallLoading
+                }
+            }
+            Message::EnterInstanceInstall => {
+                self.installation = Installation::Instance;
+                if self.have_minecraft_versions_loaded.is_some() && self.have_minecraft_versions_loaded.unwrap() {
+                    self.page = Page::InstanceInstall;
+                } else {
+                    self.page = Page::ClientInstallLoading
+                }
+            }
             Message::Retry => {
                 self.have_minecraft_versions_loaded = None;
                 self.have_loader_versions_loaded = None;
-                return Command::batch([
-                    // TODO - This is a redundance that should be a separate fn
-                    Command::perform(fetch_minecraft_versions(), |result| {
-                        if result.is_ok() {
-                            Message::SetMinecraftVersions(result.unwrap())
-                        } else {
-                            println!("Couldn't fetch Minecraft versions! {}", result.unwrap_err());
-                            Message::FailMinecraftVersions
-                        }
-                    }),
-                    Command::perform(fetch_loader_versions(), |result| {
-                        if result.is_ok() {
-                            Message::SetLoaderVersions(result.unwrap())
-                        } else {
-                            println!("Couldn't fetch Minecraft versions! {}", result.unwrap_err());
-                            Message::FailLoaderVersions
-                        }
-                    }),
-                ]);
+                return fetch_versions(&self.meta);
             },
             Message::SetMinecraftVersions(versions) => {
                 self.minecraft_versions = versions.clone();
-                self.selected_minecraft_version = versions.iter().filter(|v| v.stable).cloned().next();
+                self.offline_since = self.meta.offline_since();
+                self.selected_minecraft_version = VersionSelector::LatestStable.select_minecraft(&versions).cloned();
                 self.have_minecraft_versions_loaded = Some(true);
                 if self.page == Page::ClientInstallLoading {
-                    self.page = Page::ClientInstall
+                    self.page = self.install_page();
                 }
             },
             Message::FailMinecraftVersions => {
@@ -195,25 +560,39 @@ impl Application for Installer {
             Message::SelectMinecraftVersion(version) => self.selected_minecraft_version = Some(version),
             Message::SetLoaderVersions(versions) => {
                 self.loader_versions = versions.clone();
-                self.selected_loader_version = versions.iter().filter(|v| !v.version.contains('-')).cloned().next();
+                self.offline_since = self.meta.offline_since();
+                self.selected_loader_version = VersionSelector::LatestStable.select_loader(&versions).cloned();
             },
             Message::FailLoaderVersions => {},
             Message::SelectLoaderVersion(version) => self.selected_loader_version = Some(version),
-            Message::DirectoryInputChangeButtonPressed => {
+            Message::DirectoryInputChangeButtonPressed(installation) => {
+                let directory = self.directory_mut(installation);
+
                 let mut dialog = FileDialog::new();
                 let working_dir = std::env::current_dir();
                 
-                if self.directory.is_dir() {
-                    dialog = dialog.set_directory(&self.directory);
+                if directory.is_dir() {
+                    dialog = dialog.set_directory(&directory);
                 } else if working_dir.is_ok() {
                     dialog = dialog.set_directory(working_dir.as_deref().unwrap())
                 }
                 let result = dialog.pick_folder();
 
                 match result {
-                    Some(path) => self.directory = path,
+                    Some(path) => *directory = path,
                     None => ()
                 }
+
+                if self.page == Page::Uninstall {
+                    self.scan_client_installs();
+                }
+                if self.page == Page::Migrate {
+                    self.scan_fabric_migrations();
+                }
+                if self.page == Page::Installed {
+                    self.scan_client_installs();
+                    self.server_install = find_server_install(&self.server_directory);
+                }
             },
             Message::ShowMinecraftSnapshotsCheckmarkChanged(show_minecraft_snapshots) => {
                 self.show_minecraft_snapshots = show_minecraft_snapshots;
@@ -225,14 +604,23 @@ impl Application for Installer {
             Message::ShowLoaderBetasCheckmarkChanged(show_loader_betas) => {
                 self.show_loader_betas = show_loader_betas;
                 let old_selected_loader_version = self.selected_loader_version.clone();
-                if old_selected_loader_version.is_some() && old_selected_loader_version.unwrap().version.contains('-') {
-                    self.selected_loader_version = (self.loader_versions.iter().filter(|v| self.show_loader_betas || !v.version.contains('-'))).cloned().next();
+                if old_selected_loader_version.is_some() && old_selected_loader_version.unwrap().is_beta() {
+                    self.selected_loader_version = (self.loader_versions.iter().filter(|v| self.show_loader_betas || !v.is_beta())).cloned().next();
                 }
             },
             Message::CreateProfileCheckmarkChanged(create_profile) => self.create_profile = create_profile,
+            Message::DownloadLibrariesCheckmarkChanged(download_libraries) => self.download_libraries = download_libraries,
+            Message::UpgradeExistingCheckmarkChanged(upgrade_existing) => self.upgrade_existing = upgrade_existing,
+            Message::RemoveOldVersionsCheckmarkChanged(remove_old_versions) => self.remove_old_versions = remove_old_versions,
+            Message::DownloadServerJarCheckmarkChanged(download_server_jar) => self.download_server_jar = download_server_jar,
+            Message::GenerateScriptCheckmarkChanged(generate_script) => self.generate_script = generate_script,
+            Message::ExportZipCheckmarkChanged(export_zip) => self.export_zip = export_zip,
+            Message::TransformerCheckmarkChanged(index, enabled) => {
+                if let Some(transformer) = self.transformers.get_mut(index) {
+                    transformer.enabled = enabled;
+                }
+            },
             Message::Install => {
-                self.is_installing = true;
-
                 if self.selected_minecraft_version.is_none() {
                     return Command::none();
                 }
@@ -241,24 +629,228 @@ impl Application for Installer {
                     return Command::none();
                 }
 
-                return Command::perform(
-                    install_client(ClientInstallation {
-                        minecraft_version: self.selected_minecraft_version.clone().unwrap(),
-                        loader_version: self.selected_loader_version.clone().unwrap(),
-                        install_location: self.directory.clone(),
-                        generate_profile: self.create_profile
-                    }),
-                    |result| {
-                        if result.is_ok() {
-                            Message::InstallationDone
+                let transformers = self.transformers.clone();
+
+                let (pending, conflicts) = match self.installation {
+                    Installation::Client => {
+                        let minecraft_version = self.selected_minecraft_version.as_ref().unwrap();
+                        let loader_version = self.selected_loader_version.as_ref().unwrap();
+                        let upgrade = self.upgrade_existing.then(|| Upgrade {
+                            from: upgradable_versions(&self.directory, minecraft_version, loader_version).unwrap_or_else(|e| {
+                                log!("Couldn't look for versions to upgrade! {}", e);
+                                Vec::new()
+                            }),
+                            remove_old: self.remove_old_versions,
+                            copy_profiles: false,
+                            separate_game_dirs: false,
+                        });
+
+                        let args = ClientInstallation {
+                            minecraft_version: self.selected_minecraft_version.clone().unwrap(),
+                            loader_version: self.selected_loader_version.clone().unwrap(),
+                            install_location: self.directory.clone(),
+                            generate_profile: self.create_profile,
+                            download_libraries: self.download_libraries,
+                            transformers,
+                            suffix: None,
+                            upgrade,
+                        };
+                        let conflicts = client_conflicts(&args);
+                        (PendingInstall::Client(args), conflicts)
+                    },
+                    Installation::Server => {
+                        let args = ServerInstallation {
+                            minecraft_version: self.selected_minecraft_version.clone().unwrap(),
+                            loader_version: self.selected_loader_version.clone().unwrap(),
+                            install_location: self.server_directory.clone(),
+                            download_jar: self.download_server_jar,
+                            generate_script: self.generate_script,
+                            script_options: StartScriptOptions::default(),
+                            transformers,
+                            version_manifest_url: match version_manifest_url(None) {
+                                Ok(url) => url,
+                                Err(e) => {
+                                    log!("Couldn't load the version manifest URL! {}", e);
+                                    MOJANG_VERSION_MANIFEST.to_owned()
+                                },
+                            },
+                        };
+                        let conflicts = server_conflicts(&args);
+                        (PendingInstall::Server(args), conflicts)
+                    },
+                    Installation::Instance => {
+                        let minecraft_version = self.selected_minecraft_version.clone().unwrap();
+                        let name = InstanceInstallation::default_name(&minecraft_version);
+                        let target = if self.export_zip {
+                            InstanceTarget::Zip(self.instances_directory.join(format!("{}.zip", name)))
                         } else {
-                            // TODO - Error handle this
-                            Message::InstallationDone
-                        }
-                    }
-                );
+                            InstanceTarget::Directory(self.instances_directory.clone())
+                        };
+
+                        let args = InstanceInstallation {
+                            minecraft_version,
+                            loader_version: self.selected_loader_version.clone().unwrap(),
+                            name,
+                            target,
+                            transformers,
+                        };
+                        let conflicts = instance_conflicts(&args);
+                        (PendingInstall::Instance(args), conflicts)
+                    },
+                };
+
+                if !conflicts.is_empty() {
+                    self.conflicts = conflicts;
+                    self.pending_install = Some(pending);
+                    self.page = Page::ConfirmOverwrite;
+                    return Command::none();
+                }
+
+                return self.start_install(pending);
+            },
+            Message::ReplaceExisting => {
+                if let Some(pending) = self.pending_install.take() {
+                    self.page = match pending {
+                        PendingInstall::Migrate(..) => Page::Migrate,
+                        _ => self.install_page(),
+                    };
+                    return self.start_install(pending);
+                }
+            },
+            Message::KeepBoth => {
+                if let Some(PendingInstall::Client(mut args)) = self.pending_install.take() {
+                    args.suffix = Some(unused_suffix(&args));
+                    self.page = self.install_page();
+                    return self.start_install(PendingInstall::Client(args));
+                }
+            },
+            Message::CancelOverwrite => {
+                self.page = match self.pending_install.take() {
+                    Some(PendingInstall::Migrate(..)) => Page::Migrate,
+                    _ => self.install_page(),
+                };
+                self.conflicts.clear();
             },
+            Message::CancelInstall => self.install_cancel.cancel(),
+            Message::InstallProgress(event) => self.install_progress = Some(event),
             Message::InstallationDone => self.is_installing = false,
+            Message::InstallationFailed(error) if matches!(*error, InstallError::Cancelled) => {
+                log!("Installation cancelled");
+                self.is_installing = false;
+            },
+            Message::InstallationFailed(error) => {
+                log!("Installation failed! {}", error.details());
+                self.is_installing = false;
+                self.install_error = Some(error);
+                self.show_error_details = false;
+                self.page = Page::InstallFailed;
+            },
+            Message::RetryInstall => {
+                self.install_error = None;
+                self.page = self.install_page();
+                return self.update(Message::Install);
+            },
+            Message::ToggleErrorDetails => self.show_error_details = !self.show_error_details,
+            Message::CopyErrorDetails => {
+                if let Some(error) = &self.install_error {
+                    return clipboard::write(error.details());
+                }
+            },
+            Message::EnterUninstall => {
+                self.installation = Installation::Client;
+                self.uninstall_status = None;
+                self.scan_client_installs();
+                self.page = Page::Uninstall;
+            },
+            Message::EnterInstalled => {
+                self.scan_client_installs();
+                self.server_install = find_server_install(&self.server_directory);
+                self.page = Page::Installed;
+            },
+            Message::SelectUninstall(index, selected) => {
+                if let Some((_, is_selected)) = self.client_installs.get_mut(index) {
+                    *is_selected = selected;
+                }
+            },
+            Message::UninstallSelected => {
+                if self.client_installs.iter().any(|(_, selected)| *selected) {
+                    self.page = Page::ConfirmUninstall;
+                }
+            },
+            Message::ConfirmUninstall => {
+                self.page = Page::Uninstall;
+                let selected: Vec<ClientInstall> = self.client_installs.iter()
+                    .filter(|(_, selected)| *selected)
+                    .map(|(install, _)| install.clone())
+                    .collect();
+
+                self.uninstall_status = Some(match uninstall_clients(&self.directory, &selected) {
+                    Ok(()) => format!("Removed {} version(s)", selected.len()),
+                    Err(e) => {
+                        log!("Uninstalling failed! {}", e.details());
+                        e.to_string()
+                    },
+                });
+                self.scan_client_installs();
+            },
+            Message::EnterMigrate => {
+                self.installation = Installation::Client;
+                self.migrate_status = None;
+                self.scan_fabric_migrations();
+                self.page = Page::Migrate;
+            },
+            Message::SelectMigration(index, selected) => {
+                if let Some((_, is_selected)) = self.fabric_migrations.get_mut(index) {
+                    *is_selected = selected;
+                }
+            },
+            Message::ReplaceFabricApiCheckmarkChanged(replace_fabric_api) => self.replace_fabric_api = replace_fabric_api,
+            Message::MigrateSelected => {
+                // Migrations go to the latest stable loader, there's no version picker on the page
+                let loader_version = match VersionSelector::LatestStable.select_loader(&self.loader_versions) {
+                    Some(loader_version) => loader_version.clone(),
+                    None => {
+                        self.migrate_status = Some(String::from("No stable Quilt Loader version is available"));
+                        return Command::none();
+                    },
+                };
+
+                let mut migrations = Vec::new();
+                let mut unsupported = Vec::new();
+                for (migration, _) in self.fabric_migrations.iter().filter(|(_, selected)| *selected) {
+                    match self.minecraft_versions.iter().find(|version| version.version == migration.minecraft_version) {
+                        Some(minecraft_version) => {
+                            let installation = migration.installation(&self.directory, minecraft_version.clone(), loader_version.clone(), self.transformers.clone(), self.replace_fabric_api);
+                            migrations.push((migration.clone(), installation));
+                        },
+                        None => unsupported.push(format!("Quilt doesn't support Minecraft {}", migration.minecraft_version)),
+                    }
+                }
+
+                self.migrate_status = None;
+                let conflicts: Vec<Conflict> = migrations.iter().flat_map(|(_, installation)| client_conflicts(installation)).collect();
+                let pending = PendingInstall::Migrate(migrations, unsupported);
+                if !conflicts.is_empty() {
+                    self.conflicts = conflicts;
+                    self.pending_install = Some(pending);
+                    self.page = Page::ConfirmOverwrite;
+                    return Command::none();
+                }
+
+                return self.start_install(pending);
+            },
+            Message::MigrationDone(status) => {
+                self.is_installing = false;
+                self.migrate_status = Some(status);
+                self.scan_fabric_migrations();
+            },
+            Message::OpenLogFile => {
+                if let Some(path) = log_path() {
+                    if let Err(e) = open_path(&path) {
+                        log!("Couldn't open the log file! {}", e);
+                    }
+                }
+            },
             // _ => {}
         }
 
@@ -277,8 +869,13 @@ impl Application for Installer {
             // TODO - Actually code the other pages
             Page::Main => {
                 let hello = text("Hello!");
-                let button = button(text("Client")).on_press(Message::EnterClientInstall);
-                let column = column![hello, button];
+                let client_button = button(text("Client")).on_press(Message::EnterClientInstall);
+                let server_button = button(text("Server")).on_press(Message::EnterServerInstall);
+                let instance_button = button(text("Prism / MultiMC")).on_press(Message::EnterInstanceInstall);
+                let installed_button = button(text("Installed")).on_press(Message::EnterInstalled).style(Button::Secondary);
+                let uninstall_button = button(text("Uninstall")).on_press(Message::EnterUninstall).style(Button::Secondary);
+                let migrate_button = button(text("Migrate from Fabric")).on_press(Message::EnterMigrate).style(Button::Secondary);
+                let column = column![hello, client_button, server_button, instance_button, installed_button, uninstall_button, migrate_button];
                 column.into()
             },
             Page::ClientInstallLoading => {
@@ -290,59 +887,37 @@ impl Application for Installer {
                 }
             },
             Page::ClientInstall => {
-                let minecraft_version_label = text("Minecraft Version:").font(POPPINS_SEMIBOLD_FONT).width(Length::Units(185));
-                let minecraft_version_pick_list = pick_list(
-                    Cow::from_iter((self.minecraft_versions.iter().filter(|v| self.show_minecraft_snapshots || v.stable)).cloned()),
-                    self.selected_minecraft_version.clone(),
-                    Message::SelectMinecraftVersion
-                )
-                .width(Length::Units(185));
-        
-                let loader_version_label = text("Quilt Loader Version:").font(POPPINS_SEMIBOLD_FONT).width(Length::Units(185));
-                let loader_version_pick_list = pick_list(
-                    Cow::from_iter((self.loader_versions.iter().filter(|v| self.show_loader_betas || !v.version.contains('-'))).cloned()),
-                    self.selected_loader_version.clone(),
-                    Message::SelectLoaderVersion
-                )
-                .width(Length::Units(185));
-        
-                let versions_row = row![
-                    column![minecraft_version_label, minecraft_version_pick_list].width(Length::Units(185)).spacing(2),
-                    column![loader_version_label, loader_version_pick_list].width(Length::Units(185)).spacing(2),
-                ].spacing(10);
-                    
-                let directory_label = text("Directory:").font(POPPINS_SEMIBOLD_FONT).height(Length::Units(30));
-                let directory_button = button(text("Q").width(Length::Units(30)).horizontal_alignment(Horizontal::Center)).width(Length::Units(30)).on_press(Message::DirectoryInputChangeButtonPressed);
-                let directory_label_row = row![directory_label, horizontal_space(Length::Fill), directory_button].width(Length::Units(380));
-        
-                let directory_path = text(&self.directory.to_str().unwrap()).width(Length::Units(380)).font(POPPINS_REGULAR_FONT).size(16);
-                let directory_column = column![directory_label_row, directory_path].spacing(2);
-                
                 let options_label = text("Options:").width(Length::Units(380)).font(POPPINS_SEMIBOLD_FONT);
                 let show_snapshots_checkbox = checkbox("Show Snapshots", self.show_minecraft_snapshots, Message::ShowMinecraftSnapshotsCheckmarkChanged).width(Length::Units(380));
                 let show_loader_betas_checkbox = checkbox("Show Loader Betas", self.show_loader_betas, Message::ShowLoaderBetasCheckmarkChanged).width(Length::Units(380));
                 let create_profile_checkbox = checkbox("Create Profile", self.create_profile, Message::CreateProfileCheckmarkChanged).width(Length::Units(380));
+                let download_libraries_checkbox = checkbox("Download Libraries", self.download_libraries, Message::DownloadLibrariesCheckmarkChanged).width(Length::Units(380));
+                let upgrade_existing_checkbox = checkbox("Upgrade Existing Profiles", self.upgrade_existing, Message::UpgradeExistingCheckmarkChanged).width(Length::Units(380));
+                let remove_old_versions_checkbox = checkbox("Remove Old Versions", self.remove_old_versions, Message::RemoveOldVersionsCheckmarkChanged).width(Length::Units(380));
         
-                let install = button(text("Install Client")
+                let mut install = button(text("Install Client")
                         .horizontal_alignment(Horizontal::Center)
                         .width(Length::Units(250))
                         .font(POPPINS_SEMIBOLD_FONT)
                     )
-                    .on_press(Message::Install)
                     .padding(10);
-                
-                // This is a placeholder for a separate "Installing" view!
-                let installing_text = text("Installing...");
+                if !self.is_installing {
+                    install = install.on_press(Message::Install);
+                }
         
-                let mut page = iced::widget::column![
-                    versions_row,
+                let page = iced::widget::column![
+                    self.version_pickers(),
                     vertical_space(Length::Units(2)),
-                    directory_column,
+                    self.directory_chooser(&self.directory, Installation::Client),
                     vertical_space(Length::Units(2)),
                     options_label,
                     show_snapshots_checkbox,
                     show_loader_betas_checkbox,
                     create_profile_checkbox,
+                    download_libraries_checkbox,
+                    upgrade_existing_checkbox,
+                    remove_old_versions_checkbox,
+                    self.transformer_checkboxes(),
                     vertical_space(Length::Fill),
                     install,
                     vertical_space(Length::Units(5)),
@@ -351,14 +926,332 @@ impl Application for Installer {
                 .spacing(5)
                 .padding(5)
                 .width(Length::Fill);
-                
-                if self.is_installing {
-                    page = page.push(installing_text);
+
+                self.with_install_status(page)
+            },
+            Page::ServerDownload => {
+                let options_label = text("Options:").width(Length::Units(380)).font(POPPINS_SEMIBOLD_FONT);
+                let show_snapshots_checkbox = checkbox("Show Snapshots", self.show_minecraft_snapshots, Message::ShowMinecraftSnapshotsCheckmarkChanged).width(Length::Units(380));
+                let show_loader_betas_checkbox = checkbox("Show Loader Betas", self.show_loader_betas, Message::ShowLoaderBetasCheckmarkChanged).width(Length::Units(380));
+                let download_server_jar_checkbox = checkbox("Download Server Jar", self.download_server_jar, Message::DownloadServerJarCheckmarkChanged).width(Length::Units(380));
+                let generate_script_checkbox = checkbox("Generate Start Script", self.generate_script, Message::GenerateScriptCheckmarkChanged).width(Length::Units(380));
+
+                let mut install = button(text("Install Server")
+                        .horizontal_alignment(Horizontal::Center)
+                        .width(Length::Units(250))
+                        .font(POPPINS_SEMIBOLD_FONT)
+                    )
+                    .padding(10);
+                if !self.is_installing {
+                    install = install.on_press(Message::Install);
                 }
 
-                page.into()
+                let page = iced::widget::column![
+                    self.version_pickers(),
+                    vertical_space(Length::Units(2)),
+                    self.directory_chooser(&self.server_directory, Installation::Server),
+                    vertical_space(Length::Units(2)),
+                    options_label,
+                    show_snapshots_checkbox,
+                    show_loader_betas_checkbox,
+                    download_server_jar_checkbox,
+                    generate_script_checkbox,
+                    self.transformer_checkboxes(),
+                    vertical_space(Length::Fill),
+                    install,
+                    vertical_space(Length::Units(5)),
+                ]
+                .align_items(Alignment::Center)
+                .spacing(5)
+                .padding(5)
+                .width(Length::Fill);
+
+                self.with_install_status(page)
+            },
+            Page::InstanceInstall => {
+                let options_label = text("Options:").width(Length::Units(380)).font(POPPINS_SEMIBOLD_FONT);
+                let show_snapshots_checkbox = checkbox("Show Snapshots", self.show_minecraft_snapshots, Message::ShowMinecraftSnapshotsCheckmarkChanged).width(Length::Units(380));
+                let show_loader_betas_checkbox = checkbox("Show Loader Betas", self.show_loader_betas, Message::ShowLoaderBetasCheckmarkChanged).width(Length::Units(380));
+                let export_zip_checkbox = checkbox("Export as Zip", self.export_zip, Message::ExportZipCheckmarkChanged).width(Length::Units(380));
+
+                let mut install = button(text("Create Instance")
+                        .horizontal_alignment(Horizontal::Center)
+                        .width(Length::Units(250))
+                        .font(POPPINS_SEMIBOLD_FONT)
+                    )
+                    .padding(10);
+                if !self.is_installing {
+                    install = install.on_press(Message::Install);
+                }
+
+                let page = iced::widget::column![
+                    self.version_pickers(),
+                    vertical_space(Length::Units(2)),
+                    self.directory_chooser(&self.instances_directory, Installation::Instance),
+                    vertical_space(Length::Units(2)),
+                    options_label,
+                    show_snapshots_checkbox,
+                    show_loader_betas_checkbox,
+                    export_zip_checkbox,
+                    self.transformer_checkboxes(),
+                    vertical_space(Length::Fill),
+                    install,
+                    vertical_space(Length::Units(5)),
+                ]
+                .align_items(Alignment::Center)
+                .spacing(5)
+                .padding(5)
+                .width(Length::Fill);
+
+                self.with_install_status(page)
+            },
+            Page::Uninstall => {
+                let title = text("Uninstall").font(POPPINS_SEMIBOLD_FONT).size(24);
+
+                let installs: Element<'_, Message, iced::Renderer<Theme>> = if self.client_installs.is_empty() {
+                    text("No Quilt versions are installed here.").font(POPPINS_REGULAR_FONT).size(14).into()
+                } else {
+                    let list = self.client_installs.iter().enumerate().fold(column![].spacing(5), |list, (index, (install, selected))| {
+                        let mut label = install.version_id.clone();
+                        if install.dir.is_none() {
+                            label.push_str(" (version missing)");
+                        }
+                        if !install.profiles.is_empty() {
+                            label.push_str(&format!(", {} profile(s)", install.profiles.len()));
+                        }
+                        list.push(checkbox(label, *selected, move |selected| Message::SelectUninstall(index, selected)).width(Length::Units(370)))
+                    });
+                    scrollable(list).height(Length::Units(180)).into()
+                };
+
+                let mut uninstall = button(text("Uninstall Selected").font(POPPINS_SEMIBOLD_FONT)).padding(10);
+                if self.client_installs.iter().any(|(_, selected)| *selected) {
+                    uninstall = uninstall.on_press(Message::UninstallSelected);
+                }
+
+                let mut page = column![
+                    title,
+                    self.directory_chooser(&self.directory, Installation::Client),
+                    vertical_space(Length::Units(2)),
+                    installs,
+                    vertical_space(Length::Fill),
+                ]
+                .align_items(Alignment::Center)
+                .spacing(5)
+                .padding(5)
+                .width(Length::Fill);
+
+                if let Some(status) = &self.uninstall_status {
+                    page = page.push(text(status).font(POPPINS_REGULAR_FONT).size(14));
+                }
+
+                let actions = row![
+                    button(text("Back")).on_press(Message::UpdatePage(Page::Main)).style(Button::Secondary),
+                    uninstall,
+                ]
+                .align_items(Alignment::Center)
+                .spacing(10);
+
+                page.push(actions).push(vertical_space(Length::Units(5))).into()
+            },
+            Page::Migrate => {
+                let title = text("Migrate from Fabric").font(POPPINS_SEMIBOLD_FONT).size(24);
+
+                let migrations: Element<'_, Message, iced::Renderer<Theme>> = if self.fabric_migrations.is_empty() {
+                    text("No Fabric versions are installed here.").font(POPPINS_REGULAR_FONT).size(14).into()
+                } else {
+                    let list = self.fabric_migrations.iter().enumerate().fold(column![].spacing(5), |list, (index, (migration, selected))| {
+                        let mut label = format!("Minecraft {}", migration.minecraft_version);
+                        if !migration.profiles.is_empty() {
+                            label.push_str(&format!(", {} profile(s)", migration.profiles.len()));
+                        }
+                        list.push(checkbox(label, *selected, move |selected| Message::SelectMigration(index, selected)).width(Length::Units(370)))
+                    });
+                    scrollable(list).height(Length::Units(150)).into()
+                };
+
+                let replace_fabric_api_checkbox = checkbox("Replace Fabric API with Quilted Fabric API", self.replace_fabric_api, Message::ReplaceFabricApiCheckmarkChanged).width(Length::Units(380));
+
+                let mut migrate = button(text("Migrate Selected").font(POPPINS_SEMIBOLD_FONT)).padding(10);
+                if !self.is_installing && self.fabric_migrations.iter().any(|(_, selected)| *selected) {
+                    migrate = migrate.on_press(Message::MigrateSelected);
+                }
+
+                let mut page = column![
+                    title,
+                    self.directory_chooser(&self.directory, Installation::Client),
+                    vertical_space(Length::Units(2)),
+                    migrations,
+                    replace_fabric_api_checkbox,
+                    vertical_space(Length::Fill),
+                ]
+                .align_items(Alignment::Center)
+                .spacing(5)
+                .padding(5)
+                .width(Length::Fill);
+
+                if let Some(status) = &self.migrate_status {
+                    page = page.push(text(status).font(POPPINS_REGULAR_FONT).size(14));
+                }
+
+                let actions = row![
+                    button(text("Back")).on_press(Message::UpdatePage(Page::Main)).style(Button::Secondary),
+                    migrate,
+                ]
+                .align_items(Alignment::Center)
+                .spacing(10);
+
+                self.with_install_status(page.push(actions).push(vertical_space(Length::Units(5))))
+            },
+            Page::Installed => {
+                let title = text("Installed").font(POPPINS_SEMIBOLD_FONT).size(24);
+                let latest = VersionSelector::LatestStable.select_loader(&self.loader_versions);
+
+                let client_label = text("Clients").font(POPPINS_SEMIBOLD_FONT).size(20).width(Length::Units(370));
+                let mut list = column![client_label, self.directory_chooser(&self.directory, Installation::Client)].spacing(5);
+                if self.client_installs.is_empty() {
+                    list = list.push(text("None").font(POPPINS_REGULAR_FONT).size(14));
+                }
+                for (install, _) in &self.client_installs {
+                    list = list
+                        .push(text(&install.version_id).size(16))
+                        .push(text(describe_versions(&install.loader_version, &install.minecraft_version, latest)).font(POPPINS_REGULAR_FONT).size(14).width(Length::Units(370)));
+                }
+
+                let server_label = text("Server").font(POPPINS_SEMIBOLD_FONT).size(20).width(Length::Units(370));
+                list = list
+                    .push(vertical_space(Length::Units(5)))
+                    .push(server_label)
+                    .push(self.directory_chooser(&self.server_directory, Installation::Server));
+                list = match &self.server_install {
+                    Some(server) => list.push(text(describe_versions(&server.loader_version, &server.minecraft_version, latest)).font(POPPINS_REGULAR_FONT).size(14).width(Length::Units(370))),
+                    None => list.push(text("None").font(POPPINS_REGULAR_FONT).size(14)),
+                };
+
+                column![
+                    title,
+                    scrollable(list).height(Length::Units(260)),
+                    vertical_space(Length::Fill),
+                    button(text("Back")).on_press(Message::UpdatePage(Page::Main)).style(Button::Secondary),
+                    vertical_space(Length::Units(5)),
+                ]
+                .align_items(Alignment::Center)
+                .spacing(5)
+                .padding(5)
+                .width(Length::Fill)
+                .into()
+            },
+            Page::ConfirmOverwrite => {
+                let title = text("Already installed").font(POPPINS_SEMIBOLD_FONT).size(24);
+                let message = text("Installing will replace the following:").width(Length::Units(380)).horizontal_alignment(Horizontal::Center);
+
+                let conflicts = self.conflicts.iter().fold(column![].spacing(2), |column, conflict| {
+                    column.push(text(conflict.to_string()).font(POPPINS_REGULAR_FONT).size(14).width(Length::Units(370)))
+                });
+
+                let mut actions = row![
+                    button(text("Cancel")).on_press(Message::CancelOverwrite).style(Button::Secondary),
+                ]
+                .spacing(10);
+                // Only client profiles can be installed next to each other
+                if let Some(PendingInstall::Client(_)) = self.pending_install {
+                    actions = actions.push(button(text("Keep both")).on_press(Message::KeepBoth).style(Button::Secondary));
+                }
+                actions = actions.push(button(text("Replace").font(POPPINS_SEMIBOLD_FONT)).on_press(Message::ReplaceExisting));
+
+                column![
+                    title,
+                    message,
+                    scrollable(conflicts).height(Length::Units(160)),
+                    vertical_space(Length::Fill),
+                    actions,
+                    vertical_space(Length::Units(5)),
+                ]
+                .align_items(Alignment::Center)
+                .spacing(5)
+                .padding(5)
+                .width(Length::Fill)
+                .into()
+            },
+            Page::ConfirmUninstall => {
+                let title = text("Uninstall").font(POPPINS_SEMIBOLD_FONT).size(24);
+                let message = text("Uninstalling will remove the following:").width(Length::Units(380)).horizontal_alignment(Horizontal::Center);
+
+                let removed = self.client_installs.iter()
+                    .filter(|(_, selected)| *selected)
+                    .flat_map(|(install, _)| install.dir.iter().cloned().map(Conflict::VersionDirectory)
+                        .chain(install.profiles.iter().cloned().map(Conflict::LauncherProfile)))
+                    .fold(column![].spacing(2), |column, removed| {
+                        column.push(text(removed.to_string()).font(POPPINS_REGULAR_FONT).size(14).width(Length::Units(370)))
+                    });
+
+                let actions = row![
+                    button(text("Cancel")).on_press(Message::UpdatePage(Page::Uninstall)).style(Button::Secondary),
+                    button(text("Uninstall").font(POPPINS_SEMIBOLD_FONT)).on_press(Message::ConfirmUninstall),
+                ]
+                .spacing(10);
+
+                column![
+                    title,
+                    message,
+                    scrollable(removed).height(Length::Units(160)),
+                    vertical_space(Length::Fill),
+                    actions,
+                    vertical_space(Length::Units(5)),
+                ]
+                .align_items(Alignment::Center)
+                .spacing(5)
+                .padding(5)
+                .width(Length::Fill)
+                .into()
+            },
+            Page::InstallFailed => {
+                let title = text("Installation failed").font(POPPINS_SEMIBOLD_FONT).size(24);
+                let message = match &self.install_error {
+                    Some(error) => error.to_string(),
+                    None => String::from("Something went wrong while installing."),
+                };
+                let message_text = text(message).width(Length::Units(380)).horizontal_alignment(Horizontal::Center);
+
+                let details_button = button(text(if self.show_error_details { "Hide technical details" } else { "Show technical details" }).size(16))
+                    .on_press(Message::ToggleErrorDetails)
+                    .style(Button::Text);
+
+                let mut page = column![
+                    title,
+                    message_text,
+                    details_button,
+                ]
+                .align_items(Alignment::Center)
+                .spacing(5)
+                .padding(5)
+                .width(Length::Fill);
+
+                if self.show_error_details {
+                    if let Some(error) = &self.install_error {
+                        let details = text(error.details()).font(POPPINS_REGULAR_FONT).size(14).width(Length::Units(370));
+                        page = page.push(scrollable(details).height(Length::Units(120)));
+                    }
+                }
+
+                let actions = row![
+                    button(text("Back")).on_press(Message::UpdatePage(self.install_page())).style(Button::Secondary),
+                    button(text("Copy details")).on_press(Message::CopyErrorDetails).style(Button::Secondary),
+                    button(text("Retry").font(POPPINS_SEMIBOLD_FONT)).on_press(Message::RetryInstall),
+                ]
+                .spacing(10);
+
+                page = page.push(vertical_space(Length::Fill)).push(actions);
+
+                if let Some(path) = log_path() {
+                    let log_link = button(text(format!("Log file: {}", path.display())).font(POPPINS_REGULAR_FONT).size(14))
+                        .on_press(Message::OpenLogFile)
+                        .style(Button::Text);
+                    page = page.push(log_link);
+                }
+
+                page.push(vertical_space(Length::Units(5))).into()
             },
-            Page::ServerDownload => todo!(),
         };
 
         let all = column![
@@ -369,6 +1262,29 @@ impl Application for Installer {
         all.into()
     }
 
+    fn subscription(&self) -> Subscription<Self::Message> {
+        if !self.is_installing {
+            return Subscription::none();
+        }
+
+        subscription::unfold(self.install_attempt, ProgressSubscription::Starting(self.progress_receiver.clone()), |state| async move {
+            match state {
+                ProgressSubscription::Starting(receiver) => {
+                    let receiver = receiver.lock().unwrap().take();
+                    match receiver {
+                        Some(receiver) => (None, ProgressSubscription::Receiving(receiver)),
+                        None => (None, ProgressSubscription::Finished),
+                    }
+                },
+                ProgressSubscription::Receiving(mut receiver) => match receiver.recv().await {
+                    Some(event) => (Some(Message::InstallProgress(event)), ProgressSubscription::Receiving(receiver)),
+                    None => (None, ProgressSubscription::Finished),
+                },
+                ProgressSubscription::Finished => std::future::pending().await,
+            }
+        })
+    }
+
     fn theme(&self) -> Self::Theme {
         Theme::Light
     }
@@ -0,0 +1,215 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use zip::ZipArchive;
+
+use crate::error::{InstallError, IoResultExt};
+use crate::installer::{LaunchProfiles, LoaderVersion, MinecraftVersion, SERVER_JAR, SERVER_LAUNCHER_PROPERTIES, SERVER_LAUNCH_JAR};
+use crate::log::{log, warning};
+use crate::profile::VersionProfile;
+use crate::selector::parse_lenient;
+
+const CLIENT_VERSION_PREFIX: &str = "quilt-loader-";
+const LOADER_ARTIFACT: &str = "org.quiltmc:quilt-loader";
+const FABRIC_VERSION_PREFIX: &str = "fabric-loader-";
+const FABRIC_LOADER_ARTIFACT: &str = "net.fabricmc:fabric-loader";
+// Either of these is on the class path, and both are versioned like Minecraft
+const MAPPINGS_ARTIFACTS: [&str; 2] = ["net.fabricmc:intermediary", "org.quiltmc:hashed"];
+
+/// A Quilt (or Fabric) version in a Minecraft directory, and the launcher profiles that start it
+#[derive(Debug, Clone)]
+pub struct ClientInstall {
+    pub version_id: String,
+    /// `None` if only launcher profiles are left of the version
+    pub dir: Option<PathBuf>,
+    pub profiles: Vec<String>,
+    pub loader_version: Option<String>,
+    pub minecraft_version: Option<String>,
+}
+
+/// A Quilt server launcher in a server directory
+#[derive(Debug, Clone)]
+pub struct ServerInstall {
+    pub dir: PathBuf,
+    pub loader_version: Option<String>,
+    pub minecraft_version: Option<String>,
+    /// The vanilla jar the launcher starts, if there's one
+    pub server_jar: Option<String>,
+}
+
+/// Whether a newer stable loader than the installed one is available
+fn is_outdated(loader_version: &str, latest: &LoaderVersion) -> bool {
+    match (parse_lenient(loader_version), parse_lenient(&latest.version)) {
+        (Some(installed), Some(latest)) => installed < latest,
+        _ => loader_version != latest.version,
+    }
+}
+
+/// e.g. `Quilt Loader 0.17.5 for Minecraft 1.19.2, update available: 0.17.6`
+pub fn describe_versions(loader_version: &Option<String>, minecraft_version: &Option<String>, latest: Option<&LoaderVersion>) -> String {
+    let loader = loader_version.as_deref().unwrap_or("unknown");
+    let minecraft = minecraft_version.as_deref().unwrap_or("unknown");
+    let mut description = format!("Quilt Loader {} for Minecraft {}", loader, minecraft);
+
+    match (loader_version, latest) {
+        (Some(installed), Some(latest)) if is_outdated(installed, latest) => description.push_str(&format!(", update available: {}", latest)),
+        (Some(_), Some(_)) => description.push_str(", up to date"),
+        _ => {},
+    }
+
+    description
+}
+
+pub fn find_client_installs(minecraft_dir: &Path) -> Result<Vec<ClientInstall>, InstallError> {
+    find_versions(minecraft_dir, CLIENT_VERSION_PREFIX, LOADER_ARTIFACT)
+}
+
+/// The versions the Fabric installer created, with the Fabric Loader version as `loader_version`
+pub fn find_fabric_installs(minecraft_dir: &Path) -> Result<Vec<ClientInstall>, InstallError> {
+    find_versions(minecraft_dir, FABRIC_VERSION_PREFIX, FABRIC_LOADER_ARTIFACT)
+}
+
+fn find_versions(minecraft_dir: &Path, version_prefix: &str, loader_artifact: &str) -> Result<Vec<ClientInstall>, InstallError> {
+    let mut installs: Vec<ClientInstall> = Vec::new();
+
+    let versions_dir = minecraft_dir.join("versions");
+    if versions_dir.is_dir() {
+        for entry in std::fs::read_dir(&versions_dir).with_path(&versions_dir)? {
+            let path = entry.with_path(&versions_dir)?.path();
+            let version_id = path.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_owned();
+            if path.is_dir() && version_id.starts_with(version_prefix) {
+                let (loader_version, minecraft_version) = read_client_versions(&path.join(format!("{}.json", version_id)), loader_artifact);
+                installs.push(ClientInstall { version_id, dir: Some(path), profiles: Vec::new(), loader_version, minecraft_version });
+            }
+        }
+    }
+
+    let profiles_json = minecraft_dir.join("launcher_profiles.json");
+    if profiles_json.exists() {
+        let profiles = LaunchProfiles::load(&profiles_json)?;
+        for (key, profile) in &profiles.profiles {
+            let version_id = match LaunchProfiles::last_version_id(profile) {
+                Some(version_id) if version_id.starts_with(version_prefix) => version_id,
+                _ => continue,
+            };
+
+            match installs.iter_mut().find(|install| install.version_id == version_id) {
+                Some(install) => install.profiles.push(key.clone()),
+                None => installs.push(ClientInstall {
+                    version_id: version_id.to_owned(),
+                    dir: None,
+                    profiles: vec![key.clone()],
+                    loader_version: None,
+                    minecraft_version: None,
+                }),
+            }
+        }
+    }
+
+    for install in &mut installs {
+        install.profiles.sort();
+    }
+    installs.sort_by(|a, b| a.version_id.cmp(&b.version_id));
+
+    Ok(installs)
+}
+
+/// The installed versions an installation of these versions would upgrade, which are older loaders on the same Minecraft version
+pub fn upgradable_versions(minecraft_dir: &Path, minecraft_version: &MinecraftVersion, loader_version: &LoaderVersion) -> Result<Vec<String>, InstallError> {
+    Ok(find_client_installs(minecraft_dir)?
+        .into_iter()
+        .filter(|install| install.minecraft_version.as_deref() == Some(minecraft_version.version.as_str()))
+        .filter(|install| install.loader_version.as_deref().is_some_and(|installed| is_outdated(installed, loader_version)))
+        .map(|install| install.version_id)
+        .collect())
+}
+
+// The launch json inherits from the Minecraft version and has the loader as a library
+fn read_client_versions(json_path: &Path, loader_artifact: &str) -> (Option<String>, Option<String>) {
+    let profile = match File::open(json_path).ok().and_then(|file| serde_json::from_reader::<_, VersionProfile>(file).ok()) {
+        Some(profile) => profile,
+        None => return (None, None),
+    };
+
+    let loader_version = profile.libraries.iter()
+        .filter_map(|library| library.coordinate().ok())
+        .find(|coordinate| coordinate.is_artifact(loader_artifact))
+        .map(|coordinate| coordinate.version);
+
+    (loader_version, profile.inherits_from)
+}
+
+pub fn find_server_install(server_dir: &Path) -> Option<ServerInstall> {
+    let launch_jar = server_dir.join(SERVER_LAUNCH_JAR);
+    if !launch_jar.is_file() {
+        return None;
+    }
+
+    let class_path = match read_class_path(&launch_jar) {
+        Ok(class_path) => class_path,
+        Err(e) => {
+            warning!("Couldn't read {}: {}", launch_jar.display(), e);
+            Vec::new()
+        },
+    };
+
+    let server_jar = std::fs::read_to_string(server_dir.join(SERVER_LAUNCHER_PROPERTIES)).ok()
+        .and_then(|properties| properties.lines().find_map(|line| line.strip_prefix("serverJar=").map(|jar| jar.trim().to_owned())))
+        .or_else(|| server_dir.join(SERVER_JAR).is_file().then(|| SERVER_JAR.to_owned()));
+
+    Some(ServerInstall {
+        dir: server_dir.to_owned(),
+        loader_version: class_path_version(&class_path, LOADER_ARTIFACT),
+        minecraft_version: MAPPINGS_ARTIFACTS.iter().find_map(|artifact| class_path_version(&class_path, artifact)),
+        server_jar,
+    })
+}
+
+fn read_class_path(launch_jar: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut jar = ZipArchive::new(File::open(launch_jar)?)?;
+    let mut manifest = String::new();
+    jar.by_name("META-INF/MANIFEST.MF")?.read_to_string(&mut manifest)?;
+
+    // Undo the wrapping of long lines
+    let manifest = manifest.replace("\r\n ", "");
+    Ok(manifest.lines()
+        .find_map(|line| line.strip_prefix("Class-Path: "))
+        .map(|class_path| class_path.split(' ').map(str::to_owned).collect())
+        .unwrap_or_default())
+}
+
+// Class path entries are maven paths, so the version is the directory after the artifact
+fn class_path_version(class_path: &[String], artifact_id: &str) -> Option<String> {
+    let artifact_path = format!("/{}/", artifact_id.replace(['.', ':'], "/"));
+    class_path.iter().find_map(|entry| {
+        let (_, rest) = entry.split_once(&artifact_path)?;
+        rest.split('/').next().map(str::to_owned)
+    })
+}
+
+/// Removes the versions and every launcher profile that starts one of them
+pub fn uninstall_clients(minecraft_dir: &Path, installs: &[ClientInstall]) -> Result<(), InstallError> {
+    // Profiles go first, so the launcher never shows one whose version is already gone
+    let profiles_json = minecraft_dir.join("launcher_profiles.json");
+    if profiles_json.exists() {
+        let mut profiles = LaunchProfiles::load(&profiles_json)?;
+        let before = profiles.profiles.len();
+        profiles.profiles.retain(|_, profile| {
+            !LaunchProfiles::last_version_id(profile).is_some_and(|version_id| installs.iter().any(|install| install.version_id == version_id))
+        });
+
+        if profiles.profiles.len() != before {
+            profiles.save(&profiles_json)?;
+        }
+    }
+
+    for install in installs {
+        if let Some(dir) = &install.dir {
+            log!("Removing {}", dir.display());
+            std::fs::remove_dir_all(dir).with_path(dir)?;
+        }
+    }
+
+    Ok(())
+}
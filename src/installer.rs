@@ -1,17 +1,44 @@
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::fs::File;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
-use anyhow::{anyhow, Result};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use reqwest::StatusCode;
+use sha1::digest::DynDigest;
+use sha1::Sha1;
+use sha2::Sha512;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+use crate::cancel::CancelToken;
+use crate::config::Config;
+use crate::error::{InstallError, IoResultExt};
+use crate::log::{log, warning};
+use crate::meta::MetaSource;
+use crate::profile::{Library, VersionProfile};
+use crate::progress::{InstallStep, Progress};
+use crate::transform::{apply_transformers, ProfileTransformer};
 use crate::ICON;
 
+pub const SERVER_LAUNCH_JAR: &str = "quilt-server-launch.jar";
+pub const SERVER_JAR: &str = "server.jar";
+pub const SERVER_LAUNCHER_PROPERTIES: &str = "quilt-server-launcher.properties";
+const SYSTEMD_UNIT: &str = "quilt-server.service";
+const DEFAULT_SERVER_LAUNCHER_MAIN_CLASS: &str = "org.quiltmc.loader.impl.launch.server.QuiltServerLauncher";
+pub const MOJANG_VERSION_MANIFEST: &str = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+pub const MODRINTH_API: &str = "https://api.modrinth.com/v2";
+// Quilted Fabric API is published as the `qsl` project on Modrinth
+const QUILTED_FABRIC_API_PROJECT: &str = "qsl";
+const FABRIC_API_MOD_ID: &str = "fabric-api";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Installation {
     Client,
     Server,
+    Instance,
 }
 
 #[derive(Debug, Clone)]
@@ -19,7 +46,48 @@ pub struct ClientInstallation {
     pub minecraft_version: MinecraftVersion,
     pub loader_version: LoaderVersion,
     pub install_location: PathBuf,
-    pub generate_profile: bool
+    pub generate_profile: bool,
+    /// Download the libraries too, instead of leaving that to the launcher
+    pub download_libraries: bool,
+    pub transformers: Vec<ProfileTransformer>,
+    /// Appended to the profile name to install next to an existing profile
+    pub suffix: Option<u32>,
+    pub upgrade: Option<Upgrade>,
+}
+
+/// Moves the launcher profiles of older versions over to the new one, instead of adding another profile
+#[derive(Debug, Clone)]
+pub struct Upgrade {
+    /// The version ids to upgrade from, e.g. `quilt-loader-0.17.5-1.19.2`
+    pub from: Vec<String>,
+    /// Delete the old version directories, which no profile uses anymore
+    pub remove_old: bool,
+    /// Add Quilt copies of the old profiles and leave them as they are, e.g. for Fabric profiles
+    pub copy_profiles: bool,
+    /// Give the copies their own game directory, see [`quilt_game_dir`]
+    pub separate_game_dirs: bool,
+}
+
+impl ClientInstallation {
+    pub fn profile_name(&self) -> String {
+        let name = format!("quilt-loader-{}-{}", self.loader_version.version, self.minecraft_version.version);
+        match self.suffix {
+            Some(suffix) => format!("{}-{}", name, suffix),
+            None => name,
+        }
+    }
+
+    fn updates_launcher_profiles(&self) -> bool {
+        self.generate_profile || self.upgrade.as_ref().is_some_and(|upgrade| !upgrade.from.is_empty())
+    }
+
+    fn display_name(&self) -> String {
+        let name = format!("quilt-loader-{}", self.minecraft_version.version);
+        match self.suffix {
+            Some(suffix) => format!("{} ({})", name, suffix),
+            None => name,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -28,11 +96,57 @@ pub struct ServerInstallation {
     pub loader_version: LoaderVersion,
     pub install_location: PathBuf,
     pub download_jar: bool,
-    pub generate_script: bool
+    pub generate_script: bool,
+    pub script_options: StartScriptOptions,
+    pub transformers: Vec<ProfileTransformer>,
+    pub version_manifest_url: String,
 }
 
+/// How the generated start scripts launch the server
+#[derive(Debug, Clone)]
+pub struct StartScriptOptions {
+    pub java: String,
+    pub min_memory: Option<String>,
+    pub max_memory: String,
+    pub jvm_args: Vec<String>,
+    pub nogui: bool,
+    /// The user the server runs as from a systemd service unit, which is only generated with one
+    pub systemd_user: Option<String>,
+}
+
+impl Default for StartScriptOptions {
+    fn default() -> Self {
+        StartScriptOptions {
+            java: String::from("java"),
+            min_memory: None,
+            max_memory: String::from("2G"),
+            jvm_args: Vec::new(),
+            nogui: true,
+            systemd_user: None,
+        }
+    }
+}
+
+impl StartScriptOptions {
+    fn command(&self, quote: fn(&str) -> String) -> String {
+        let mut command = vec![quote(&self.java)];
+        if let Some(min_memory) = &self.min_memory {
+            command.push(quote(&format!("-Xms{}", min_memory)));
+        }
+        command.push(quote(&format!("-Xmx{}", self.max_memory)));
+        command.extend(self.jvm_args.iter().map(|arg| quote(arg)));
+        command.push(String::from("-jar"));
+        command.push(String::from(SERVER_LAUNCH_JAR));
+        if self.nogui {
+            command.push(String::from("nogui"));
+        }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+        command.join(" ")
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MinecraftVersion {
     pub version: String,
     pub stable: bool
@@ -45,7 +159,7 @@ impl Display for MinecraftVersion {
 }
 
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LoaderVersion {
     pub separator: String,
     pub build: u32,
@@ -53,98 +167,1405 @@ pub struct LoaderVersion {
     pub version: String,
 }
 
+impl LoaderVersion {
+    pub fn is_beta(&self) -> bool {
+        self.version.contains('-')
+    }
+}
+
 impl Display for LoaderVersion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.version)
     }
 }
 
-pub async fn fetch_minecraft_versions() -> Result<Vec<MinecraftVersion>> {
-    Ok(reqwest::get("https://meta.quiltmc.org/v3/versions/game").await?.json().await?)
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LaunchProfiles {
+    pub profiles: HashMap<String, serde_json::Value>,
+    settings: serde_json::Value,
+    version: u32
+}
+
+impl LaunchProfiles {
+    pub fn load(path: &Path) -> Result<Self, InstallError> {
+        let file = File::open(path).with_path(path)?;
+        serde_json::from_reader(file).map_err(|source| InstallError::MalformedLauncherProfiles { path: path.to_owned(), source })
+    }
+
+    /// Writes to a temporary file first, so the launcher never sees a half written file
+    pub fn save(&self, path: &Path) -> Result<(), InstallError> {
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+
+        let file = File::create(&temp_path).with_path(&temp_path)?;
+        serde_json::to_writer_pretty(file, self).map_err(std::io::Error::from).with_path(&temp_path)?;
+        std::fs::rename(&temp_path, path).with_path(path)
+    }
+
+    /// The version a launcher profile starts
+    pub fn last_version_id(profile: &serde_json::Value) -> Option<&str> {
+        profile.get("lastVersionId").and_then(serde_json::Value::as_str)
+    }
+
+    /// The directory a launcher profile plays in, which is the Minecraft directory unless the profile sets one
+    pub fn game_dir(minecraft_dir: &Path, profile: &serde_json::Value) -> PathBuf {
+        profile.get("gameDir")
+            .and_then(serde_json::Value::as_str)
+            .filter(|game_dir| !game_dir.is_empty())
+            .map_or_else(|| minecraft_dir.to_owned(), PathBuf::from)
+    }
+}
+
+/// Where the Quilt copy of a profile plays, so its mods don't change what the old profile loads.
+/// Copies of profiles in the Minecraft directory go to a `quilt` folder in it, the others next to their game directory.
+pub fn quilt_game_dir(minecraft_dir: &Path, game_dir: &Path) -> PathBuf {
+    match game_dir.file_name() {
+        Some(name) if game_dir != minecraft_dir => {
+            let mut name = name.to_owned();
+            name.push("-quilt");
+            game_dir.with_file_name(name)
+        },
+        _ => minecraft_dir.join("quilt"),
+    }
 }
 
-pub async fn fetch_loader_versions() -> Result<Vec<LoaderVersion>> {
-    Ok(reqwest::get("https://meta.quiltmc.org/v3/versions/loader").await?.json().await?)
+/// Something an installation would replace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    VersionDirectory(PathBuf),
+    LauncherProfile(String),
+    ServerFile(PathBuf),
+    Instance(PathBuf),
 }
 
-#[derive(Serialize, Deserialize)]
-struct LaunchProfiles {
-    profiles: HashMap<String, serde_json::Value>,
-    settings: serde_json::Value,
-    version: u32
+impl Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Conflict::VersionDirectory(path) => write!(f, "Version directory {}", path.display()),
+            Conflict::LauncherProfile(name) => write!(f, "Launcher profile `{}`", name),
+            Conflict::ServerFile(path) => write!(f, "Server file {}", path.display()),
+            Conflict::Instance(path) => write!(f, "Instance {}", path.display()),
+        }
+    }
 }
 
-pub async fn install_client(args: ClientInstallation) -> Result<()> {
-    println!("Installing client: {:#?}", args);
+pub fn client_conflicts(args: &ClientInstallation) -> Vec<Conflict> {
+    let profile_name = args.profile_name();
+    let mut conflicts = Vec::new();
+
+    let profile_dir = args.install_location.join("versions").join(&profile_name);
+    if profile_dir.exists() {
+        conflicts.push(Conflict::VersionDirectory(profile_dir));
+    }
+
+    let profiles = File::open(args.install_location.join("launcher_profiles.json")).ok()
+        .and_then(|file| serde_json::from_reader::<_, LaunchProfiles>(file).ok());
+    if let Some(profiles) = profiles {
+        if args.generate_profile && profiles.profiles.contains_key(&profile_name) {
+            conflicts.push(Conflict::LauncherProfile(profile_name));
+        }
+
+        if let Some(upgrade) = args.upgrade.as_ref().filter(|upgrade| upgrade.copy_profiles) {
+            let copies = profiles.profiles.iter()
+                .filter(|(_, profile)| LaunchProfiles::last_version_id(profile).is_some_and(|version_id| upgrade.from.iter().any(|old| old == version_id)))
+                .map(|(key, _)| format!("{}-quilt", key))
+                .filter(|copy| profiles.profiles.contains_key(copy));
+            conflicts.extend(copies.map(Conflict::LauncherProfile));
+        }
+    }
+
+    conflicts
+}
+
+/// Finds the first suffix that lets the installation sit next to the existing ones
+pub fn unused_suffix(args: &ClientInstallation) -> u32 {
+    let mut args = args.clone();
+    for suffix in 2.. {
+        args.suffix = Some(suffix);
+        if client_conflicts(&args).is_empty() {
+            return suffix;
+        }
+    }
+
+    unreachable!()
+}
+
+pub fn server_conflicts(args: &ServerInstallation) -> Vec<Conflict> {
+    let mut files = vec![SERVER_LAUNCH_JAR];
+    if args.download_jar {
+        files.extend([SERVER_JAR, SERVER_LAUNCHER_PROPERTIES]);
+    }
+    if args.generate_script {
+        files.extend(["start.sh", "start.bat"]);
+        if args.script_options.systemd_user.is_some() {
+            files.push(SYSTEMD_UNIT);
+        }
+    }
+
+    files.into_iter()
+        .map(|file| args.install_location.join(file))
+        .filter(|path| path.exists())
+        .map(Conflict::ServerFile)
+        .collect()
+}
+
+pub async fn install_client(meta: &dyn MetaSource, args: ClientInstallation, progress: &Progress, cancel: &CancelToken) -> Result<(), InstallError> {
+    log!("Installing client {} in {}", args.profile_name(), args.install_location.display());
 
     // Verify install location
     if !args.install_location.exists() {
-        return Err(anyhow!("Target directory doesn't exist: {:?}", args.install_location));
+        return Err(InstallError::DirectoryNotFound(args.install_location));
+    }
+    if args.updates_launcher_profiles() && !args.install_location.join("launcher_profiles.json").exists() {
+        return Err(InstallError::LauncherProfilesNotFound(args.install_location));
     }
 
-    // Resolve profile directory
-    let profile_name = format!("quilt-loader-{}-{}", args.loader_version.version, args.minecraft_version.version);
-    let mut profile_dir = args.install_location.clone();
-    profile_dir.push("versions");
-    profile_dir.push(&profile_name);
+    // Download launch json
+    progress.step(InstallStep::DownloadingProfile);
+    let response = cancel.run(meta.client_profile(&args.minecraft_version, &args.loader_version)).await?;
 
-    // Delete existing profile
-    if profile_dir.exists() {
-        std::fs::remove_dir_all(&profile_dir)?;
+    let mut profile: VersionProfile = serde_json::from_str(&response).map_err(|source| InstallError::InvalidMetaResponse {
+        what: format!("the launch json of Quilt Loader {} on Minecraft {}", &args.loader_version, &args.minecraft_version),
+        source,
+    })?;
+
+    apply_transformers(&args.transformers, &mut profile, &args.minecraft_version, &args.loader_version);
+
+    let library_steps = if args.download_libraries { profile.libraries.len() } else { 0 };
+    progress.set_total_steps(2 + library_steps + args.updates_launcher_profiles() as usize);
+
+    let mut downloads = Downloads::new(progress, cancel);
+    if args.download_libraries {
+        let result = download_libraries(&profile.libraries, &args.install_location, &mut downloads).await
+            .and_then(|_| cancel.check());
+        if let Err(error) = result {
+            downloads.undo();
+            return Err(error);
+        }
     }
 
-    // Create directory
-    std::fs::create_dir_all(&profile_dir)?;
+    // Only local writes are left, which can't be cancelled
+    cancel.check()?;
+
+    progress.step(InstallStep::WritingFiles);
+    let profile_name = args.profile_name();
+    profile.id = profile_name.clone();
+    let mut transaction = Transaction::new(&args.install_location)?;
+
+    let result = stage_client_files(&args, &profile, &profile_name, &transaction, progress).and_then(|()| {
+        transaction.commit(&profile_name, &args.install_location.join("versions").join(&profile_name))?;
+        if args.updates_launcher_profiles() {
+            transaction.commit("launcher_profiles.json", &args.install_location.join("launcher_profiles.json"))?;
+        }
+
+        if let Some(upgrade) = args.upgrade.as_ref().filter(|upgrade| upgrade.remove_old) {
+            for old_version in upgrade.from.iter().filter(|old_version| *old_version != &profile_name) {
+                let old_dir = args.install_location.join("versions").join(old_version);
+                if old_dir.exists() {
+                    log!("Removing superseded version {}", old_version);
+                    transaction.remove(old_version, &old_dir)?;
+                }
+            }
+        }
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => {
+            transaction.finish();
+            downloads.finish();
+        },
+        Err(error) => {
+            log!("Client installation failed, restoring previous files: {}", error);
+            transaction.rollback();
+            downloads.undo();
+            return Err(error);
+        },
+    }
+
+    Ok(())
+}
+
+fn stage_client_files(args: &ClientInstallation, profile: &VersionProfile, profile_name: &str, transaction: &Transaction, progress: &Progress) -> Result<(), InstallError> {
+    let profile_dir = transaction.staged(profile_name);
+    std::fs::create_dir_all(&profile_dir).with_path(&profile_dir)?;
 
     // NOTE: This is an empty jar file to make the vanilla launcher happy
-    let mut jar_path = profile_dir.clone();
-    jar_path.push(format!("{}.jar", &profile_name));
-    File::create(jar_path)?;
-    
+    let jar_path = profile_dir.join(format!("{}.jar", profile_name));
+    File::create(&jar_path).with_path(&jar_path)?;
+
     // Create launch json
-    let mut json_path = profile_dir.clone();
-    json_path.push(format!("{}.json", &profile_name));
-    let mut file = File::create(json_path)?;
+    let json_path = profile_dir.join(format!("{}.json", profile_name));
+    let mut file = File::create(&json_path).with_path(&json_path)?;
+    serde_json::to_writer(&mut file, profile).map_err(std::io::Error::from).with_path(&json_path)?;
 
-    // Download launch json
-    let response = reqwest::get(format!("https://meta.quiltmc.org/v3/versions/loader/{}/{}/profile/json", &args.minecraft_version.version, &args.loader_version.version)).await?.text().await?;
+    // Generate profile
+    if args.updates_launcher_profiles() {
+        progress.step(InstallStep::UpdatingLauncherProfiles);
+        let profiles_json = args.install_location.join("launcher_profiles.json");
+        let mut profiles = LaunchProfiles::load(&profiles_json)?;
 
-    // Hack-Fix:
-    // Quilt-meta specifies both hashed and intermediary, but providing both to quilt-loader causes it to silently fail remapping.
-    // This really shouldn't be fixed here in the installer, but we need a solution now.
-    let mut json: serde_json::Value = serde_json::from_str(&response).unwrap();
-    let libs = json.as_object_mut().unwrap().get_mut("libraries").unwrap().as_array_mut().unwrap();
-    libs.retain(|lib| !lib.as_object().unwrap().get("name").unwrap().as_str().unwrap().starts_with("org.quiltmc:hashed"));
-    let response = serde_json::to_string(&json).unwrap();
-    // End of hack-fix
+        // Upgraded profiles only get their version changed, everything the user set up stays
+        let mut upgraded = 0;
+        if let Some(upgrade) = &args.upgrade {
+            let old_profiles: Vec<String> = profiles.profiles.iter()
+                .filter(|(_, profile)| LaunchProfiles::last_version_id(profile).is_some_and(|version_id| upgrade.from.iter().any(|old| old == version_id)))
+                .map(|(key, _)| key.clone())
+                .collect();
 
-    std::io::copy(&mut response.as_bytes(), &mut file)?;
+            for key in old_profiles {
+                if upgrade.copy_profiles {
+                    let game_dir = upgrade.separate_game_dirs.then(|| quilt_game_dir(&args.install_location, &LaunchProfiles::game_dir(&args.install_location, &profiles.profiles[&key])));
+                    let copy = copy_profile(&profiles.profiles[&key], profile_name, game_dir);
+                    profiles.profiles.insert(format!("{}-quilt", key), copy);
+                } else if let Some(profile) = profiles.profiles.get_mut(&key).and_then(serde_json::Value::as_object_mut) {
+                    profile.insert("lastVersionId".into(), serde_json::Value::String(profile_name.to_owned()));
+                }
+                upgraded += 1;
+            }
+            log!("{} {} launcher profile(s) to {}", if upgrade.copy_profiles { "Copied" } else { "Upgraded" }, upgraded, profile_name);
+        }
 
+        if args.generate_profile && upgraded == 0 {
+            let mut new_profile = serde_json::Map::new();
+            new_profile.insert("name".into(), serde_json::Value::String(args.display_name()));
+            new_profile.insert("type".into(), serde_json::Value::String("custom".into()));
+            new_profile.insert("created".into(), serde_json::Value::String(format!("{:?}", Utc::now())));
+            new_profile.insert("lastVersionId".into(), serde_json::Value::String(profile_name.to_owned()));
+            new_profile.insert("icon".into(), serde_json::Value::String(format!("data:image/png;base64,{}", base64::encode(ICON))));
+            if args.upgrade.as_ref().is_some_and(|upgrade| upgrade.separate_game_dirs) {
+                let game_dir = quilt_game_dir(&args.install_location, &args.install_location);
+                new_profile.insert("gameDir".into(), serde_json::Value::String(game_dir.to_string_lossy().into_owned()));
+            }
+            profiles.profiles.insert(profile_name.to_owned(), serde_json::Value::Object(new_profile));
+        }
 
-    // Generate profile
-    if args.generate_profile {
-        let mut profiles_json = args.install_location.clone();
-        profiles_json.push("launcher_profiles.json");
+        let staged_json = transaction.staged("launcher_profiles.json");
+        let write_file = File::create(&staged_json).with_path(&staged_json)?;
+        serde_json::to_writer_pretty(write_file, &profiles).map_err(std::io::Error::from).with_path(&staged_json)?;
+    }
+
+    Ok(())
+}
+
+// Only the settings that still make sense with a different loader are copied
+fn copy_profile(profile: &serde_json::Value, profile_name: &str, game_dir: Option<PathBuf>) -> serde_json::Value {
+    let mut copy = serde_json::Map::new();
+    let name = profile.get("name").and_then(serde_json::Value::as_str).filter(|name| !name.is_empty()).unwrap_or(profile_name);
+    copy.insert("name".into(), serde_json::Value::String(format!("{} (Quilt)", name)));
+    copy.insert("type".into(), serde_json::Value::String("custom".into()));
+    copy.insert("created".into(), serde_json::Value::String(format!("{:?}", Utc::now())));
+    copy.insert("lastVersionId".into(), serde_json::Value::String(profile_name.to_owned()));
+    for key in ["gameDir", "javaArgs", "icon"] {
+        if let Some(value) = profile.get(key) {
+            copy.insert(key.into(), value.clone());
+        }
+    }
+    if let Some(game_dir) = game_dir {
+        copy.insert("gameDir".into(), serde_json::Value::String(game_dir.to_string_lossy().into_owned()));
+    }
+
+    serde_json::Value::Object(copy)
+}
+
+/// Collects the output of an installation next to where it goes, and only moves it into place once all of it is ready.
+/// Anything that gets replaced is backed up until the installation is finished.
+struct Transaction {
+    dir: PathBuf,
+    committed: Vec<(PathBuf, Option<PathBuf>)>,
+}
+
+impl Transaction {
+    fn new(install_location: &Path) -> Result<Self, InstallError> {
+        // Kept in the install location so moving files into place is a rename on the same file system
+        let dir = install_location.join(format!(".quilt-installer-{}", Utc::now().timestamp_millis()));
+        for sub_dir in ["staging", "backup"] {
+            let path = dir.join(sub_dir);
+            std::fs::create_dir_all(&path).with_path(&path)?;
+        }
+
+        Ok(Transaction { dir, committed: Vec::new() })
+    }
+
+    fn staged(&self, name: &str) -> PathBuf {
+        self.dir.join("staging").join(name)
+    }
+
+    /// Moves a staged file or directory to its target, backing up whatever was there before
+    fn commit(&mut self, name: &str, target: &Path) -> Result<(), InstallError> {
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).with_path(parent)?;
+        }
+
+        let backup = if target.exists() {
+            let backup = self.dir.join("backup").join(name);
+            std::fs::rename(target, &backup).with_path(target)?;
+            Some(backup)
+        } else {
+            None
+        };
+        self.committed.push((target.to_owned(), backup));
+
+        std::fs::rename(self.staged(name), target).with_path(target)
+    }
+
+    /// Moves a file or directory out of the way, so it's only deleted once the installation is finished
+    fn remove(&mut self, name: &str, target: &Path) -> Result<(), InstallError> {
+        let backup = self.dir.join("backup").join(name);
+        std::fs::rename(target, &backup).with_path(target)?;
+        self.committed.push((target.to_owned(), Some(backup)));
+        Ok(())
+    }
+
+    /// Puts back everything that was replaced, newest first
+    fn rollback(self) {
+        let mut restored = true;
+        for (target, backup) in self.committed.iter().rev() {
+            let removed = if target.is_dir() {
+                std::fs::remove_dir_all(target)
+            } else {
+                std::fs::remove_file(target)
+            };
+
+            match removed {
+                Ok(()) => {},
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {},
+                Err(error) => {
+                    warning!("Couldn't remove {}: {}", target.display(), error);
+                    restored = false;
+                    continue;
+                },
+            }
+
+            if let Some(backup) = backup {
+                if let Err(error) = std::fs::rename(backup, target) {
+                    warning!("Couldn't restore {} from {}: {}", target.display(), backup.display(), error);
+                    restored = false;
+                }
+            }
+        }
+
+        // Keep the backups around if anything couldn't be put back
+        if restored {
+            self.finish();
+        }
+    }
+
+    fn finish(self) {
+        if let Err(error) = std::fs::remove_dir_all(&self.dir) {
+            warning!("Couldn't remove {}: {}", self.dir.display(), error);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct VersionManifest {
+    versions: Vec<VersionManifestEntry>,
+}
+
+#[derive(Deserialize)]
+struct VersionManifestEntry {
+    id: String,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct MojangVersion {
+    downloads: MojangDownloads,
+}
+
+#[derive(Deserialize)]
+struct MojangDownloads {
+    server: Option<MojangDownload>,
+}
+
+#[derive(Deserialize)]
+struct MojangDownload {
+    url: String,
+    sha1: String,
+}
+
+/// Uses the version manifest URL from the command line or the config file, or Mojang's own
+pub fn version_manifest_url(version_manifest_url: Option<String>) -> anyhow::Result<String> {
+    match version_manifest_url {
+        Some(url) => Ok(url),
+        None => Ok(Config::load()?.version_manifest_url.unwrap_or_else(|| MOJANG_VERSION_MANIFEST.to_owned())),
+    }
+}
+
+pub fn modrinth_api_url(modrinth_api_url: Option<String>) -> anyhow::Result<String> {
+    match modrinth_api_url {
+        Some(url) => Ok(url),
+        None => Ok(Config::load()?.modrinth_api_url.unwrap_or_else(|| MODRINTH_API.to_owned())),
+    }
+}
+
+/// A checksum published next to a download
+#[derive(Debug, Clone)]
+enum Checksum {
+    Sha1(String),
+    Sha512(String),
+}
+
+impl Checksum {
+    fn algorithm(&self) -> &'static str {
+        match self {
+            Checksum::Sha1(_) => "SHA-1",
+            Checksum::Sha512(_) => "SHA-512",
+        }
+    }
+
+    fn expected(&self) -> &str {
+        match self {
+            Checksum::Sha1(hash) | Checksum::Sha512(hash) => hash,
+        }
+    }
 
-        let read_file = File::open(&profiles_json)?;
-        let mut profiles: LaunchProfiles = serde_json::from_reader(read_file)?;
+    fn hasher(&self) -> Box<dyn DynDigest + Send> {
+        match self {
+            Checksum::Sha1(_) => Box::new(Sha1::default()),
+            Checksum::Sha512(_) => Box::new(Sha512::default()),
+        }
+    }
+
+    fn matches(&self, hasher: Box<dyn DynDigest + Send>) -> Result<(), String> {
+        let actual: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+        if actual.eq_ignore_ascii_case(self.expected()) {
+            Ok(())
+        } else {
+            Err(actual)
+        }
+    }
+
+    fn matches_file(&self, path: &Path) -> std::io::Result<bool> {
+        let mut file = File::open(path)?;
+        let mut hasher = self.hasher();
+        let mut buffer = [0; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+
+        Ok(self.matches(hasher).is_ok())
+    }
+}
+
+/// Fetches the checksum maven publishes next to an artifact, preferring SHA-512 over SHA-1
+async fn fetch_checksum(url: &str) -> Result<Checksum, InstallError> {
+    for extension in ["sha512", "sha1"] {
+        let sidecar_url = format!("{}.{}", url, extension);
+        let network_error = |source| InstallError::Network { url: sidecar_url.clone(), source };
 
-        let mut new_profile = serde_json::Map::new();
-        new_profile.insert("name".into(), serde_json::Value::String(format!("quilt-loader-{}", &args.minecraft_version.version)));
-        new_profile.insert("type".into(), serde_json::Value::String("custom".into()));
-        new_profile.insert("created".into(), serde_json::Value::String(format!("{:?}", Utc::now())));
-        new_profile.insert("lastVersionId".into(), serde_json::Value::String(profile_name.clone()));
-        new_profile.insert("icon".into(), serde_json::Value::String(format!("data:image/png;base64,{}", base64::encode(ICON))));
-        profiles.profiles.insert(profile_name, serde_json::Value::Object(new_profile));
+        let response = reqwest::get(&sidecar_url).await.map_err(network_error)?;
+        if response.status() == StatusCode::NOT_FOUND {
+            continue;
+        }
 
-        let write_file = File::create(&profiles_json)?;
-        serde_json::to_writer_pretty(write_file, &profiles)?;
+        // Some repositories put the file name after the hash
+        let body = response.error_for_status().map_err(network_error)?.text().await.map_err(network_error)?;
+        let hash = body.split_whitespace().next().unwrap_or_default().to_owned();
+        return Ok(if extension == "sha512" { Checksum::Sha512(hash) } else { Checksum::Sha1(hash) });
+    }
+
+    Err(InstallError::MissingChecksum(url.to_owned()))
+}
+
+/// Downloads libraries into `libraries/` in the maven layout and returns their paths relative to the install location
+async fn download_libraries(libraries: &[Library], install_location: &Path, downloads: &mut Downloads<'_>) -> Result<Vec<String>, InstallError> {
+    let mut paths = Vec::new();
+    for (index, library) in libraries.iter().enumerate() {
+        downloads.progress.step(InstallStep::DownloadingLibrary { index, count: libraries.len(), name: library.name.clone() });
+
+        let coordinate = library.coordinate()?;
+        let repository = library.url.as_deref().ok_or_else(|| InstallError::InvalidLibrary(library.name.clone()))?;
+        let url = coordinate.url(repository);
+        let path = coordinate.path();
+
+        let mut library_path = install_location.join("libraries");
+        library_path.push(&path);
+
+        let checksum = downloads.cancel.run(fetch_checksum(&url)).await?;
+        log!("Downloading {}", &library.name);
+        downloads.download(&url, &library_path, Some(checksum)).await?;
+        paths.push(format!("libraries/{}", &path));
+    }
+
+    Ok(paths)
+}
+
+pub async fn install_server(meta: &dyn MetaSource, args: ServerInstallation, progress: &Progress, cancel: &CancelToken) -> Result<(), InstallError> {
+    log!("Installing server for Quilt Loader {} on Minecraft {} in {}", args.loader_version, args.minecraft_version, args.install_location.display());
+
+    // Verify install location
+    if !args.install_location.exists() {
+        return Err(InstallError::DirectoryNotFound(args.install_location));
+    }
+
+    // Download server profile
+    progress.step(InstallStep::DownloadingProfile);
+    let response = cancel.run(meta.server_profile(&args.minecraft_version, &args.loader_version)).await?;
+    let mut profile: VersionProfile = serde_json::from_str(&response).map_err(|source| InstallError::InvalidMetaResponse {
+        what: format!("the server profile of Quilt Loader {} on Minecraft {}", &args.loader_version, &args.minecraft_version),
+        source,
+    })?;
+
+    apply_transformers(&args.transformers, &mut profile, &args.minecraft_version, &args.loader_version);
+
+    progress.set_total_steps(2 + profile.libraries.len() + args.download_jar as usize + args.generate_script as usize);
+
+    let mut transaction = Transaction::new(&args.install_location)?;
+    let mut downloads = Downloads::new(progress, cancel);
+    let result = install_server_files(&args, &profile, &mut transaction, &mut downloads).await;
+    match &result {
+        Ok(()) => {
+            transaction.finish();
+            downloads.finish();
+        },
+        Err(error) => {
+            log!("Server installation stopped, restoring previous files: {}", error);
+            transaction.rollback();
+            downloads.undo();
+        },
+    }
+
+    result
+}
+
+// The files in the server directory are staged and only replace the existing ones once all of them are ready
+async fn install_server_files(args: &ServerInstallation, profile: &VersionProfile, transaction: &mut Transaction, downloads: &mut Downloads<'_>) -> Result<(), InstallError> {
+    let progress = downloads.progress;
+
+    let class_path = download_libraries(&profile.libraries, &args.install_location, downloads).await?;
+
+    let mut files = vec![SERVER_LAUNCH_JAR];
+    if args.download_jar {
+        progress.step(InstallStep::DownloadingServerJar);
+        files.extend(download_server_jar(&args.version_manifest_url, &args.minecraft_version, &args.install_location, transaction, downloads).await?);
+    }
+
+    // Everything is downloaded, only local writes are left
+    downloads.cancel.check()?;
+
+    // Write launcher jar
+    progress.step(InstallStep::WritingFiles);
+    let launcher_main_class = profile.launcher_main_class.as_deref().unwrap_or(DEFAULT_SERVER_LAUNCHER_MAIN_CLASS);
+    write_server_launch_jar(&transaction.staged(SERVER_LAUNCH_JAR), launcher_main_class, &profile.main_class, &class_path)?;
+
+    if args.generate_script {
+        progress.step(InstallStep::GeneratingScripts);
+        files.extend(generate_start_scripts(&args.install_location, &args.script_options, transaction)?);
+    }
+
+    for file in files {
+        transaction.commit(file, &args.install_location.join(file))?;
     }
 
     Ok(())
 }
 
-pub async fn install_server(args: ServerInstallation) -> Result<()> {
-    println!("Installing server: {:#?}", args);
+/// Downloads files for an installation and remembers which files and directories it created, so they can be removed again
+struct Downloads<'a> {
+    progress: &'a Progress,
+    cancel: &'a CancelToken,
+    created: Vec<PathBuf>,
+    /// Files that were replaced, with where they were moved to until the downloads are finished
+    backups: Vec<(PathBuf, PathBuf)>,
+}
+
+impl<'a> Downloads<'a> {
+    fn new(progress: &'a Progress, cancel: &'a CancelToken) -> Self {
+        Downloads { progress, cancel, created: Vec::new(), backups: Vec::new() }
+    }
+
+    /// Remembers a file that is about to be written, unless it already exists
+    fn track(&mut self, path: &Path) {
+        if !path.exists() {
+            self.created.push(path.to_owned());
+        }
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> Result<(), InstallError> {
+        let mut missing: Vec<&Path> = path.ancestors().take_while(|dir| !dir.exists()).collect();
+        missing.reverse();
+
+        for dir in missing {
+            std::fs::create_dir(dir).with_path(dir)?;
+            self.created.push(dir.to_owned());
+        }
+
+        Ok(())
+    }
+
+    async fn download(&mut self, url: &str, path: &Path, checksum: Option<Checksum>) -> Result<(), InstallError> {
+        if let Some(parent) = path.parent() {
+            self.create_dir_all(parent)?;
+        }
+
+        // Downloads go to a separate file first, so an existing file is only replaced once the new one is complete
+        let mut part_path = path.as_os_str().to_owned();
+        part_path.push(".part");
+        let part_path = PathBuf::from(part_path);
+
+        // Files that are already there don't need to be downloaded again
+        if let Some(checksum) = &checksum {
+            if path.exists() && checksum.matches_file(path).with_path(path)? {
+                log!("{} is up to date", path.display());
+                return Ok(());
+            }
+        }
+
+        self.track(path);
+        self.created.push(part_path.clone());
+
+        let mut response = self.cancel.run(http_get(url)).await?;
+        let total = response.content_length();
+        let mut file = File::create(&part_path).with_path(&part_path)?;
+        let mut downloaded = 0;
+        let mut hasher = checksum.as_ref().map(Checksum::hasher);
+
+        let network_error = |source| InstallError::Network { url: url.to_owned(), source };
+        while let Some(chunk) = self.cancel.run(async { response.chunk().await.map_err(network_error) }).await? {
+            file.write_all(&chunk).with_path(&part_path)?;
+            if let Some(hasher) = &mut hasher {
+                hasher.update(&chunk);
+            }
+            downloaded += chunk.len() as u64;
+            self.progress.download(downloaded, total);
+        }
+
+        drop(file);
+
+        if let (Some(checksum), Some(hasher)) = (checksum, hasher) {
+            if let Err(actual) = checksum.matches(hasher) {
+                return Err(InstallError::ChecksumMismatch {
+                    url: url.to_owned(),
+                    algorithm: checksum.algorithm(),
+                    expected: checksum.expected().to_owned(),
+                    actual,
+                });
+            }
+        }
+
+        if path.exists() {
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(".bak");
+            let backup = PathBuf::from(backup);
+            std::fs::rename(path, &backup).with_path(path)?;
+            self.backups.push((path.to_owned(), backup));
+        }
+
+        std::fs::rename(&part_path, path).with_path(path)?;
+        self.created.retain(|created| created != &part_path);
+
+        Ok(())
+    }
+
+    /// Removes the backups of the replaced files
+    fn finish(self) {
+        for (_, backup) in &self.backups {
+            if let Err(error) = std::fs::remove_file(backup) {
+                warning!("Couldn't remove {}: {}", backup.display(), error);
+            }
+        }
+    }
+
+    /// Removes everything that was created, newest first, and puts the replaced files back
+    fn undo(self) {
+        for path in self.created.iter().rev() {
+            let result = if path.is_dir() {
+                std::fs::remove_dir(path)
+            } else {
+                std::fs::remove_file(path)
+            };
+
+            match result {
+                Ok(()) => {}
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+                Err(error) => warning!("Couldn't remove {}: {}", path.display(), error),
+            }
+        }
+
+        for (path, backup) in self.backups.iter().rev() {
+            let result = std::fs::remove_file(path).or_else(|error| if error.kind() == std::io::ErrorKind::NotFound { Ok(()) } else { Err(error) })
+                .and_then(|()| std::fs::rename(backup, path));
+            if let Err(error) = result {
+                warning!("Couldn't restore {} from {}: {}", path.display(), backup.display(), error);
+            }
+        }
+    }
+}
+
+async fn http_get(url: &str) -> Result<reqwest::Response, InstallError> {
+    let network_error = |source| InstallError::Network { url: url.to_owned(), source };
+    reqwest::get(url).await.map_err(network_error)?.error_for_status().map_err(network_error)
+}
+
+async fn fetch_json<T: DeserializeOwned>(url: &str) -> Result<T, InstallError> {
+    http_get(url).await?.json().await.map_err(|source| InstallError::Network { url: url.to_owned(), source })
+}
+
+fn write_server_launch_jar(path: &Path, launcher_main_class: &str, main_class: &str, class_path: &[String]) -> Result<(), InstallError> {
+    let mut manifest = String::new();
+    write_manifest_attribute(&mut manifest, "Manifest-Version", "1.0");
+    write_manifest_attribute(&mut manifest, "Main-Class", launcher_main_class);
+    write_manifest_attribute(&mut manifest, "Class-Path", &class_path.join(" "));
+    manifest.push_str("\r\n");
+
+    let mut jar = ZipWriter::new(File::create(path).with_path(path)?);
+    jar.start_file("META-INF/MANIFEST.MF", FileOptions::default())?;
+    jar.write_all(manifest.as_bytes()).with_path(path)?;
+    jar.start_file("quilt-server-launch.properties", FileOptions::default())?;
+    writeln!(jar, "launch.mainClass={}", main_class).with_path(path)?;
+    jar.finish()?;
+
+    Ok(())
+}
+
+// Manifest lines can't be longer than 72 bytes, longer values are continued on lines starting with a space
+fn write_manifest_attribute(manifest: &mut String, name: &str, value: &str) {
+    let line = format!("{}: {}", name, value);
+    let mut remaining = line.as_str();
+    let mut limit = 72;
+
+    while remaining.len() > limit {
+        let mut split = limit;
+        while !remaining.is_char_boundary(split) {
+            split -= 1;
+        }
+
+        let (head, tail) = remaining.split_at(split);
+        manifest.push_str(head);
+        manifest.push_str("\r\n ");
+        remaining = tail;
+        limit = 71;
+    }
+
+    manifest.push_str(remaining);
+    manifest.push_str("\r\n");
+}
+
+#[derive(Deserialize)]
+struct ModrinthVersion {
+    version_number: String,
+    files: Vec<ModrinthFile>,
+}
+
+#[derive(Deserialize)]
+struct ModrinthFile {
+    url: String,
+    filename: String,
+    primary: bool,
+    hashes: ModrinthHashes,
+}
+
+#[derive(Deserialize)]
+struct ModrinthHashes {
+    sha512: String,
+}
+
+/// Puts the latest Quilted Fabric API into every mods folder that has Fabric API, and disables Fabric API there
+pub async fn replace_fabric_api(modrinth_api_url: &str, minecraft_version: &MinecraftVersion, mods_dirs: &[PathBuf], progress: &Progress, cancel: &CancelToken) -> Result<(), InstallError> {
+    let replaced: Vec<(&PathBuf, Vec<PathBuf>)> = mods_dirs.iter()
+        .map(|mods_dir| (mods_dir, fabric_api_jars(mods_dir)))
+        .filter(|(_, jars)| !jars.is_empty())
+        .collect();
+    if replaced.is_empty() {
+        log!("No Fabric API to replace for Minecraft {}", minecraft_version);
+        return Ok(());
+    }
+
+    progress.set_total_steps(replaced.len());
+
+    let url = reqwest::Url::parse_with_params(
+        &format!("{}/project/{}/version", modrinth_api_url.trim_end_matches('/'), QUILTED_FABRIC_API_PROJECT),
+        &[("loaders", serde_json::json!(["quilt"]).to_string()), ("game_versions", serde_json::json!([minecraft_version.version]).to_string())],
+    ).map_err(|_| InstallError::QuiltedFabricApiUnavailable(minecraft_version.version.clone()))?;
+
+    // Modrinth lists the newest version first
+    let versions: Vec<ModrinthVersion> = cancel.run(fetch_json(url.as_str())).await?;
+    let (version, file) = versions.iter()
+        .find_map(|version| version.files.iter().find(|file| file.primary).or(version.files.first()).map(|file| (version, file)))
+        .ok_or_else(|| InstallError::QuiltedFabricApiUnavailable(minecraft_version.version.clone()))?;
+    log!("Replacing Fabric API with Quilted Fabric API {}", version.version_number);
+
+    let mut downloads = Downloads::new(progress, cancel);
+    for (mods_dir, _) in &replaced {
+        progress.step(InstallStep::ReplacingFabricApi(mods_dir.to_path_buf()));
+        let result = downloads.download(&file.url, &mods_dir.join(&file.filename), Some(Checksum::Sha512(file.hashes.sha512.clone()))).await;
+        if let Err(error) = result {
+            downloads.undo();
+            return Err(error);
+        }
+    }
+
+    // The launcher ignores anything that isn't a jar, so renaming is enough and easy to undo
+    let mut disabled_jars = Vec::new();
+    let result = replaced.iter().flat_map(|(_, jars)| jars).filter(|jar| !jar.ends_with(&file.filename)).try_for_each(|jar| {
+        let mut disabled = jar.as_os_str().to_owned();
+        disabled.push(".disabled");
+        log!("Disabling {}", jar.display());
+        std::fs::rename(jar, &disabled).with_path(jar)?;
+        disabled_jars.push((jar, PathBuf::from(disabled)));
+        Ok(())
+    });
+
+    if let Err(error) = result {
+        log!("Replacing Fabric API failed, enabling it again: {}", error);
+        for (jar, disabled) in disabled_jars.iter().rev() {
+            if let Err(error) = std::fs::rename(disabled, jar) {
+                warning!("Couldn't restore {} from {}: {}", jar.display(), disabled.display(), error);
+            }
+        }
+        downloads.undo();
+        return Err(error);
+    }
+
+    downloads.finish();
     Ok(())
 }
+
+fn fabric_api_jars(mods_dir: &Path) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(mods_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "jar"))
+        .filter(|path| mod_id(path).is_some_and(|id| id == FABRIC_API_MOD_ID))
+        .collect()
+}
+
+// Fabric mods declare their id in fabric.mod.json
+fn mod_id(jar_path: &Path) -> Option<String> {
+    let mut jar = zip::ZipArchive::new(File::open(jar_path).ok()?).ok()?;
+    let mod_json: serde_json::Value = serde_json::from_reader(jar.by_name("fabric.mod.json").ok()?).ok()?;
+    mod_json.get("id")?.as_str().map(str::to_owned)
+}
+
+/// Stages the server jar, unless the one in the server directory is up to date, and returns the staged files
+async fn download_server_jar(version_manifest_url: &str, minecraft_version: &MinecraftVersion, install_location: &Path, transaction: &Transaction, downloads: &mut Downloads<'_>) -> Result<Vec<&'static str>, InstallError> {
+    let manifest: VersionManifest = downloads.cancel.run(fetch_json(version_manifest_url)).await?;
+    let entry = manifest.versions.iter()
+        .find(|v| v.id == minecraft_version.version)
+        .ok_or_else(|| InstallError::ServerJarUnavailable(minecraft_version.version.clone()))?;
+
+    let version: MojangVersion = downloads.cancel.run(fetch_json(&entry.url)).await?;
+    let server = version.downloads.server.ok_or_else(|| InstallError::ServerJarUnavailable(minecraft_version.version.clone()))?;
+
+    // Tells the server launcher which jar to start Minecraft from
+    let properties_path = transaction.staged(SERVER_LAUNCHER_PROPERTIES);
+    std::fs::write(&properties_path, format!("serverJar={}\n", SERVER_JAR)).with_path(&properties_path)?;
+
+    let checksum = Checksum::Sha1(server.sha1);
+    let server_jar = install_location.join(SERVER_JAR);
+    if server_jar.exists() && checksum.matches_file(&server_jar).with_path(&server_jar)? {
+        log!("{} is up to date", server_jar.display());
+        return Ok(vec![SERVER_LAUNCHER_PROPERTIES]);
+    }
+
+    log!("Downloading Minecraft {} server jar", minecraft_version);
+    downloads.download(&server.url, &transaction.staged(SERVER_JAR), Some(checksum)).await?;
+    Ok(vec![SERVER_JAR, SERVER_LAUNCHER_PROPERTIES])
+}
+
+/// Stages the start scripts and returns their file names
+fn generate_start_scripts(install_location: &Path, options: &StartScriptOptions, transaction: &Transaction) -> Result<Vec<&'static str>, InstallError> {
+    let sh_path = transaction.staged("start.sh");
+    let sh = format!("#!/usr/bin/env sh\ncd \"$(dirname \"$0\")\"\nexec {} \"$@\"\n", options.command(sh_quote));
+    std::fs::write(&sh_path, sh).with_path(&sh_path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&sh_path, std::fs::Permissions::from_mode(0o755)).with_path(&sh_path)?;
+    }
+
+    let bat_path = transaction.staged("start.bat");
+    let bat = format!("@echo off\r\ncd /d \"%~dp0\"\r\n{} %*\r\npause\r\n", options.command(bat_quote));
+    std::fs::write(&bat_path, bat).with_path(&bat_path)?;
+
+    let mut files = vec!["start.sh", "start.bat"];
+    if let Some(user) = &options.systemd_user {
+        // systemd needs absolute paths
+        let server_dir = std::fs::canonicalize(install_location).with_path(install_location)?;
+        let unit_path = transaction.staged(SYSTEMD_UNIT);
+        std::fs::write(&unit_path, systemd_unit(&server_dir, user)).with_path(&unit_path)?;
+        files.push(SYSTEMD_UNIT);
+    }
+
+    Ok(files)
+}
+
+fn systemd_unit(server_dir: &Path, user: &str) -> String {
+    // `%` starts a specifier anywhere in a unit, and the start script's path is also a quoted argument
+    let escape = |value: &str| value.replace(['\r', '\n'], " ").replace('%', "%%");
+    let dir = escape(&server_dir.display().to_string());
+    format!(
+        "[Unit]\nDescription=Quilt Minecraft server\nAfter=network-online.target\nWants=network-online.target\n\n\
+        [Service]\nType=simple\nUser={user}\nWorkingDirectory={dir}\nExecStart=/bin/sh \"{script}/start.sh\"\nRestart=on-failure\n\n\
+        [Install]\nWantedBy=multi-user.target\n",
+        user = escape(user),
+        script = dir.replace('\\', "\\\\").replace('"', "\\\""),
+    )
+}
+
+fn sh_quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:+,@%".contains(c)) {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+fn bat_quote(arg: &str) -> String {
+    // A line break ends the command and can't be escaped, so it becomes a space
+    let arg = arg.replace(['\r', '\n'], " ").replace('%', "%%");
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || "&()^<>|\"".contains(c)) {
+        arg
+    } else {
+        format!("\"{}\"", arg.replace('"', "\"\""))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::{BufRead, BufReader};
+
+    use super::*;
+    use crate::maven::MavenCoordinate;
+    use crate::meta::LocalMetaSource;
+    use crate::selector::VersionSelector;
+    use crate::transform::default_transformers;
+
+    // Manifests wrap at 72 bytes, continuation lines start with a space
+    fn unwrap_manifest(manifest: &str) -> String {
+        manifest.replace("\r\n ", "")
+    }
+
+    #[test]
+    fn keeps_short_manifest_lines() {
+        let mut manifest = String::new();
+        write_manifest_attribute(&mut manifest, "Main-Class", "org.quiltmc.Main");
+        assert_eq!(manifest, "Main-Class: org.quiltmc.Main\r\n");
+
+        // Exactly 72 bytes still fits
+        let mut manifest = String::new();
+        write_manifest_attribute(&mut manifest, "Class-Path", &"a".repeat(72 - "Class-Path: ".len()));
+        assert_eq!(manifest.len(), 72 + 2);
+        assert!(!manifest.contains("\r\n "));
+    }
+
+    #[test]
+    fn wraps_long_manifest_lines() {
+        let class_path: Vec<String> = (0..20).map(|i| format!("libraries/org/example/library-{}/1.0/library-{}-1.0.jar", i, i)).collect();
+        let value = class_path.join(" ");
+
+        let mut manifest = String::new();
+        write_manifest_attribute(&mut manifest, "Class-Path", &value);
+
+        assert!(manifest.ends_with("\r\n"));
+        for line in manifest.trim_end_matches("\r\n").split("\r\n") {
+            assert!(line.len() <= 72, "`{}` is {} bytes", line, line.len());
+        }
+        assert_eq!(unwrap_manifest(&manifest), format!("Class-Path: {}\r\n", value));
+    }
+
+    #[test]
+    fn wraps_manifest_lines_between_characters() {
+        let value = "é".repeat(100);
+        let mut manifest = String::new();
+        write_manifest_attribute(&mut manifest, "Name", &value);
+
+        for line in manifest.trim_end_matches("\r\n").split("\r\n") {
+            assert!(line.len() <= 72);
+        }
+        assert_eq!(unwrap_manifest(&manifest), format!("Name: {}\r\n", value));
+    }
+
+    #[test]
+    fn leaves_plain_shell_arguments_alone() {
+        assert_eq!(sh_quote("java"), "java");
+        assert_eq!(sh_quote("-Xmx2G"), "-Xmx2G");
+        assert_eq!(sh_quote("-Dlog4j.configurationFile=log4j2.xml"), "-Dlog4j.configurationFile=log4j2.xml");
+    }
+
+    #[test]
+    fn quotes_hostile_shell_arguments() {
+        assert_eq!(sh_quote(""), "''");
+        assert_eq!(sh_quote("/opt/my java/bin/java"), "'/opt/my java/bin/java'");
+        assert_eq!(sh_quote("$(rm -rf ~)"), "'$(rm -rf ~)'");
+        assert_eq!(sh_quote("it's"), "'it'\\''s'");
+        assert_eq!(sh_quote("a\nb"), "'a\nb'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shell_arguments_survive_the_shell() {
+        let args = ["", "plain", "two words", "it's", "\"double\"", "$HOME `id` $(id)", "back\\slash", "line\nbreak", "*", "; exit 1"];
+        let script = format!("printf '%s\\0' {}", args.iter().map(|arg| sh_quote(arg)).collect::<Vec<_>>().join(" "));
+
+        let output = std::process::Command::new("sh").arg("-c").arg(&script).output().unwrap();
+        assert!(output.status.success());
+        let printed: Vec<&str> = std::str::from_utf8(&output.stdout).unwrap().split_terminator('\0').collect();
+        assert_eq!(printed, args);
+    }
+
+    #[test]
+    fn quotes_hostile_batch_arguments() {
+        assert_eq!(bat_quote("java"), "java");
+        assert_eq!(bat_quote(""), "\"\"");
+        assert_eq!(bat_quote("C:\\Program Files\\Java\\bin\\java.exe"), "\"C:\\Program Files\\Java\\bin\\java.exe\"");
+        assert_eq!(bat_quote("%PATH%"), "%%PATH%%");
+        assert_eq!(bat_quote("a & del *"), "\"a & del *\"");
+        assert_eq!(bat_quote("a|b"), "\"a|b\"");
+        assert_eq!(bat_quote("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert!(!bat_quote("a\r\ndel *").contains(['\r', '\n']));
+    }
+
+    #[test]
+    fn builds_start_commands() {
+        let options = StartScriptOptions {
+            java: String::from("/opt/my java/java"),
+            min_memory: Some(String::from("1G")),
+            jvm_args: vec![String::from("-Dfoo=a b")],
+            ..StartScriptOptions::default()
+        };
+
+        assert_eq!(options.command(sh_quote), "'/opt/my java/java' -Xms1G -Xmx2G '-Dfoo=a b' -jar quilt-server-launch.jar nogui");
+        assert_eq!(options.command(bat_quote), "\"/opt/my java/java\" -Xms1G -Xmx2G \"-Dfoo=a b\" -jar quilt-server-launch.jar nogui");
+    }
+
+    #[test]
+    fn escapes_systemd_units() {
+        let unit = systemd_unit(Path::new("/srv/100% \"vanilla\""), "minecraft");
+        assert!(unit.contains("\nUser=minecraft\n"));
+        assert!(unit.contains("\nWorkingDirectory=/srv/100%% \"vanilla\"\n"));
+        assert!(unit.contains("\nExecStart=/bin/sh \"/srv/100%% \\\"vanilla\\\"/start.sh\"\n"));
+    }
+
+    // `printf 'quilt' | sha1sum` and `| sha512sum`
+    const QUILT_SHA1: &str = "2a1acb3a3766ea080e72a3b09f3b3a9374a52247";
+    const QUILT_SHA512: &str = "e18c8f9a0af80b0e86c0ed88b16cc77ecc534b9d28c8041aac0f6255bd372e718d33e87b2e7ec426ba278cac2ac767e5503fb024fa85cfd56e69c8f5c8be69b6";
+
+    fn digest(checksum: &Checksum, data: &[u8]) -> Result<(), String> {
+        let mut hasher = checksum.hasher();
+        hasher.update(data);
+        checksum.matches(hasher)
+    }
+
+    #[test]
+    fn verifies_checksums() {
+        assert_eq!(digest(&Checksum::Sha1(QUILT_SHA1.to_owned()), b"quilt"), Ok(()));
+        assert_eq!(digest(&Checksum::Sha512(QUILT_SHA512.to_owned()), b"quilt"), Ok(()));
+        // Published checksums are sometimes upper case
+        assert_eq!(digest(&Checksum::Sha1(QUILT_SHA1.to_uppercase()), b"quilt"), Ok(()));
+    }
+
+    #[test]
+    fn rejects_mismatched_checksums() {
+        // The actual checksum is reported for the error message
+        assert_eq!(digest(&Checksum::Sha1(QUILT_SHA1.to_owned()), b"quilt!"), Err(String::from("75e93856e921d0d46a77267a3d96d0610cbc530f")));
+        assert!(digest(&Checksum::Sha512(QUILT_SHA512.to_owned()), b"quilt!").is_err());
+        // The right hash for the wrong algorithm doesn't count either
+        assert!(digest(&Checksum::Sha512(QUILT_SHA1.to_owned()), b"quilt").is_err());
+        assert!(digest(&Checksum::Sha1(String::new()), b"quilt").is_err());
+    }
+
+    #[test]
+    fn verifies_files() {
+        let path = std::env::temp_dir().join(format!("quilt-installer-checksum-{}", std::process::id()));
+        std::fs::write(&path, "quilt").unwrap();
+
+        let matches = Checksum::Sha512(QUILT_SHA512.to_owned()).matches_file(&path).unwrap();
+        let mismatches = Checksum::Sha1(QUILT_SHA512.to_owned()).matches_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches);
+        assert!(!mismatches);
+        assert!(Checksum::Sha1(QUILT_SHA1.to_owned()).matches_file(&path).is_err());
+    }
+
+    const LIBRARIES: [&str; 3] = ["org.quiltmc:hashed:1.19.2", "net.fabricmc:intermediary:1.19.2", "org.quiltmc:quilt-loader:0.17.6"];
+
+    fn sha1_hex(data: &[u8]) -> String {
+        let mut hasher = Checksum::Sha1(String::new()).hasher();
+        hasher.update(data);
+        hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    // The libraries of the fixture profiles, with the SHA-1 sidecars maven publishes
+    pub(crate) fn maven_files() -> HashMap<String, Vec<u8>> {
+        let mut files = HashMap::new();
+        for library in LIBRARIES {
+            let path = library.parse::<MavenCoordinate>().unwrap().path();
+            files.insert(format!("{}.sha1", path), sha1_hex(library.as_bytes()).into_bytes());
+            files.insert(path, library.as_bytes().to_vec());
+        }
+        files
+    }
+
+    // A bare bones HTTP server standing in for a maven repository or Modrinth, the files can link to it
+    pub(crate) fn serve(files: impl FnOnce(&str) -> HashMap<String, Vec<u8>>) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let files = files(&url);
+
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(&stream);
+                let _ = reader.read_line(&mut request_line);
+                let mut header = String::new();
+                while reader.read_line(&mut header).is_ok_and(|read| read > 2) {
+                    header.clear();
+                }
+
+                let path = request_line.split([' ', '?']).nth(1).unwrap_or("/").trim_start_matches('/');
+                let (status, body) = match files.get(path) {
+                    Some(body) => ("200 OK", body.as_slice()),
+                    None => ("404 Not Found", &[][..]),
+                };
+                let _ = write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len());
+                let _ = stream.write_all(body);
+            }
+        });
+
+        url
+    }
+
+    pub(crate) fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("quilt-installer-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // The fixture meta, with its libraries pointing at the local maven
+    pub(crate) fn local_meta(dir: &Path, maven_url: &str) -> LocalMetaSource {
+        fn copy(from: &Path, to: &Path, maven_url: &str) {
+            std::fs::create_dir_all(to).unwrap();
+            for entry in std::fs::read_dir(from).unwrap() {
+                let path = entry.unwrap().path();
+                let target = to.join(path.file_name().unwrap());
+                if path.is_dir() {
+                    copy(&path, &target, maven_url);
+                } else {
+                    std::fs::write(&target, std::fs::read_to_string(&path).unwrap().replace("{maven}", maven_url)).unwrap();
+                }
+            }
+        }
+
+        let root = dir.join("meta");
+        copy(&Path::new(env!("CARGO_MANIFEST_DIR")).join("test-fixtures/meta"), &root, maven_url);
+        LocalMetaSource::new(root)
+    }
+
+    pub(crate) async fn latest_versions(meta: &LocalMetaSource) -> (MinecraftVersion, LoaderVersion) {
+        let minecraft_versions = meta.minecraft_versions().await.unwrap();
+        let loader_versions = meta.loader_versions().await.unwrap();
+        (
+            VersionSelector::LatestStable.select_minecraft(&minecraft_versions).cloned().unwrap(),
+            VersionSelector::LatestStable.select_loader(&loader_versions).cloned().unwrap(),
+        )
+    }
+
+    fn client_installation(meta_versions: (MinecraftVersion, LoaderVersion), install_location: PathBuf) -> ClientInstallation {
+        ClientInstallation {
+            minecraft_version: meta_versions.0,
+            loader_version: meta_versions.1,
+            install_location,
+            generate_profile: true,
+            download_libraries: true,
+            transformers: default_transformers(),
+            suffix: None,
+            upgrade: None,
+        }
+    }
+
+    fn server_installation(meta_versions: (MinecraftVersion, LoaderVersion), install_location: PathBuf) -> ServerInstallation {
+        ServerInstallation {
+            minecraft_version: meta_versions.0,
+            loader_version: meta_versions.1,
+            install_location,
+            download_jar: false,
+            generate_script: true,
+            script_options: StartScriptOptions::default(),
+            transformers: default_transformers(),
+            version_manifest_url: MOJANG_VERSION_MANIFEST.to_owned(),
+        }
+    }
+
+    pub(crate) fn dir_entries(dir: &Path) -> Vec<String> {
+        let mut entries: Vec<String> = std::fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    #[tokio::test]
+    async fn installs_client_from_local_meta() {
+        let dir = temp_dir("client");
+        let meta = local_meta(&dir, &serve(|_| maven_files()));
+        let minecraft_dir = dir.join(".minecraft");
+        std::fs::create_dir_all(&minecraft_dir).unwrap();
+        std::fs::write(minecraft_dir.join("launcher_profiles.json"), r#"{ "profiles": {}, "settings": {}, "version": 3 }"#).unwrap();
+
+        let args = client_installation(latest_versions(&meta).await, minecraft_dir.clone());
+        install_client(&meta, args, &Progress::none(), &CancelToken::new()).await.unwrap();
+
+        let version_dir = minecraft_dir.join("versions/quilt-loader-0.17.6-1.19.2");
+        assert_eq!(dir_entries(&version_dir), ["quilt-loader-0.17.6-1.19.2.jar", "quilt-loader-0.17.6-1.19.2.json"]);
+        let profile: VersionProfile = serde_json::from_str(&std::fs::read_to_string(version_dir.join("quilt-loader-0.17.6-1.19.2.json")).unwrap()).unwrap();
+        assert_eq!(profile.id, "quilt-loader-0.17.6-1.19.2");
+
+        // The default transformers drop hashed, so only the other libraries are downloaded
+        assert_eq!(profile.libraries.len(), 2);
+        assert!(!minecraft_dir.join("libraries/org/quiltmc/hashed").exists());
+        let loader_jar = minecraft_dir.join("libraries/org/quiltmc/quilt-loader/0.17.6/quilt-loader-0.17.6.jar");
+        assert_eq!(std::fs::read(loader_jar).unwrap(), b"org.quiltmc:quilt-loader:0.17.6");
+
+        let profiles = LaunchProfiles::load(&minecraft_dir.join("launcher_profiles.json")).unwrap();
+        let launcher_profile = &profiles.profiles["quilt-loader-0.17.6-1.19.2"];
+        assert_eq!(LaunchProfiles::last_version_id(launcher_profile), Some("quilt-loader-0.17.6-1.19.2"));
+
+        // Nothing from the installation is left behind
+        assert_eq!(dir_entries(&minecraft_dir), ["launcher_profiles.json", "libraries", "versions"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn checks_for_launcher_profiles_before_downloading() {
+        let dir = temp_dir("client-without-launcher");
+        // Nothing is served, so getting past the check would fail differently
+        let meta = local_meta(&dir, &serve(|_| HashMap::new()));
+        let minecraft_dir = dir.join(".minecraft");
+        std::fs::create_dir_all(&minecraft_dir).unwrap();
+
+        let args = client_installation(latest_versions(&meta).await, minecraft_dir.clone());
+        let result = install_client(&meta, args, &Progress::none(), &CancelToken::new()).await;
+        assert!(matches!(result, Err(InstallError::LauncherProfilesNotFound(_))));
+
+        assert!(dir_entries(&minecraft_dir).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn installs_server_from_local_meta() {
+        let dir = temp_dir("server");
+        let meta = local_meta(&dir, &serve(|_| maven_files()));
+        let server_dir = dir.join("server");
+        std::fs::create_dir_all(&server_dir).unwrap();
+
+        let args = server_installation(latest_versions(&meta).await, server_dir.clone());
+        install_server(&meta, args, &Progress::none(), &CancelToken::new()).await.unwrap();
+
+        assert_eq!(dir_entries(&server_dir), ["libraries", "quilt-server-launch.jar", "start.bat", "start.sh"]);
+
+        let mut jar = zip::ZipArchive::new(File::open(server_dir.join(SERVER_LAUNCH_JAR)).unwrap()).unwrap();
+        let mut manifest = String::new();
+        jar.by_name("META-INF/MANIFEST.MF").unwrap().read_to_string(&mut manifest).unwrap();
+        let manifest = manifest.replace("\r\n ", "");
+        assert!(manifest.contains("Main-Class: org.quiltmc.loader.impl.launch.server.QuiltServerLauncher"));
+        assert!(manifest.contains("libraries/org/quiltmc/quilt-loader/0.17.6/quilt-loader-0.17.6.jar"));
+        assert!(server_dir.join("libraries/net/fabricmc/intermediary/1.19.2/intermediary-1.19.2.jar").is_file());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn undoes_failed_server_install() {
+        let dir = temp_dir("server-failure");
+        // The loader is missing from the maven, so the install fails after downloading the other libraries
+        let mut files = maven_files();
+        files.retain(|path, _| !path.contains("quilt-loader"));
+        let meta = local_meta(&dir, &serve(|_| files));
+        let server_dir = dir.join("server");
+        std::fs::create_dir_all(&server_dir).unwrap();
+        std::fs::write(server_dir.join(SERVER_LAUNCH_JAR), "previous").unwrap();
+
+        let args = server_installation(latest_versions(&meta).await, server_dir.clone());
+        assert!(install_server(&meta, args, &Progress::none(), &CancelToken::new()).await.is_err());
+
+        // A failed reinstall leaves the previous installation as it was
+        assert_eq!(dir_entries(&server_dir), [SERVER_LAUNCH_JAR]);
+        assert_eq!(std::fs::read_to_string(server_dir.join(SERVER_LAUNCH_JAR)).unwrap(), "previous");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn restores_replaced_downloads() {
+        let dir = temp_dir("replaced-download");
+        let url = serve(|_| HashMap::from([(String::from("library.jar"), b"downloaded".to_vec())]));
+        let path = dir.join("library.jar");
+        let (progress, cancel) = (Progress::none(), CancelToken::new());
+
+        // A file with the wrong checksum is downloaded again, and comes back if the downloads are undone
+        std::fs::write(&path, "outdated").unwrap();
+        let mut downloads = Downloads::new(&progress, &cancel);
+        downloads.download(&format!("{}library.jar", url), &path, Some(Checksum::Sha1(sha1_hex(b"downloaded")))).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "downloaded");
+        downloads.undo();
+        assert_eq!(dir_entries(&dir), ["library.jar"]);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "outdated");
+
+        let mut downloads = Downloads::new(&progress, &cancel);
+        downloads.download(&format!("{}library.jar", url), &path, Some(Checksum::Sha1(sha1_hex(b"downloaded")))).await.unwrap();
+        downloads.finish();
+        assert_eq!(dir_entries(&dir), ["library.jar"]);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "downloaded");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    pub(crate) fn fabric_api_jar(path: &Path) {
+        let mut jar = ZipWriter::new(File::create(path).unwrap());
+        jar.start_file("fabric.mod.json", FileOptions::default()).unwrap();
+        jar.write_all(br#"{ "schemaVersion": 1, "id": "fabric-api", "version": "0.64.0+1.19.2" }"#).unwrap();
+        jar.finish().unwrap();
+    }
+
+    // Modrinth with a single Quilted Fabric API version, downloaded as qfapi.jar
+    pub(crate) fn serve_modrinth() -> String {
+        let quilted_fabric_api = b"qfapi".to_vec();
+        let mut hasher = Checksum::Sha512(String::new()).hasher();
+        hasher.update(&quilted_fabric_api);
+        let sha512: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+
+        serve(|url| {
+            let versions = serde_json::json!([{
+                "version_number": "4.0.0-beta.16+0.64.0-1.19.2",
+                "files": [{ "url": format!("{}qfapi.jar", url), "filename": "qfapi.jar", "primary": true, "hashes": { "sha512": sha512 } }],
+            }]);
+            HashMap::from([
+                (String::from("project/qsl/version"), versions.to_string().into_bytes()),
+                (String::from("qfapi.jar"), quilted_fabric_api),
+            ])
+        })
+    }
+
+    #[tokio::test]
+    async fn undoes_failed_fabric_api_replacement() {
+        let dir = temp_dir("fabric-api");
+        let modrinth_url = serve_modrinth();
+
+        let mods_dirs = [dir.join("a/mods"), dir.join("b/mods")];
+        for mods_dir in &mods_dirs {
+            std::fs::create_dir_all(mods_dir).unwrap();
+            fabric_api_jar(&mods_dir.join("fabric-api.jar"));
+        }
+        // Disabling the second Fabric API fails, since there's a directory in the way
+        std::fs::create_dir_all(mods_dirs[1].join("fabric-api.jar.disabled/blocked")).unwrap();
+
+        let minecraft_version = MinecraftVersion { version: String::from("1.19.2"), stable: true };
+        let result = replace_fabric_api(&modrinth_url, &minecraft_version, &mods_dirs, &Progress::none(), &CancelToken::new()).await;
+        assert!(result.is_err());
+
+        assert_eq!(dir_entries(&mods_dirs[0]), ["fabric-api.jar"]);
+        assert_eq!(dir_entries(&mods_dirs[1]), ["fabric-api.jar", "fabric-api.jar.disabled"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
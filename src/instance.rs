@@ -0,0 +1,287 @@
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use crate::cancel::CancelToken;
+use crate::error::{InstallError, IoResultExt};
+use crate::installer::{Conflict, LoaderVersion, MinecraftVersion};
+use crate::log::{log, warning};
+use crate::meta::MetaSource;
+use crate::profile::VersionProfile;
+use crate::progress::{InstallStep, Progress};
+use crate::transform::{apply_transformers, ProfileTransformer};
+use crate::ICON;
+
+// Prism Launcher and PolyMC, which it was forked from, keep their data in the same layout.
+// MultiMC is portable, so there's no fixed place to look for it.
+const LAUNCHERS: [(&str, &str); 2] = [("PrismLauncher", "prismlauncher.cfg"), ("PolyMC", "polymc.cfg")];
+const PRISM_FLATPAK_DATA: &str = ".var/app/org.prismlauncher.PrismLauncher/data/PrismLauncher";
+const ICON_KEY: &str = "quilt";
+const ICON_FILE: &str = "quilt.png";
+// Windows doesn't allow these as file names, not even with an extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+// The launcher's components for the mappings libraries of the Quilt profile
+const MAPPINGS_COMPONENTS: [(&str, &str, &str); 2] = [
+    ("net.fabricmc:intermediary", "net.fabricmc.intermediary", "Intermediary Mappings"),
+    ("org.quiltmc:hashed", "org.quiltmc.hashed", "Hashed Mappings"),
+];
+
+/// Where an instance for Prism Launcher or MultiMC is created
+#[derive(Debug, Clone)]
+pub enum InstanceTarget {
+    /// The launcher's instances directory, the instance gets its own directory in it
+    Directory(PathBuf),
+    /// A zip file to import in the launcher
+    Zip(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+pub struct InstanceInstallation {
+    pub minecraft_version: MinecraftVersion,
+    pub loader_version: LoaderVersion,
+    pub name: String,
+    pub target: InstanceTarget,
+    pub transformers: Vec<ProfileTransformer>,
+}
+
+impl InstanceInstallation {
+    pub fn default_name(minecraft_version: &MinecraftVersion) -> String {
+        format!("Quilt {}", minecraft_version.version)
+    }
+
+    /// The instance directory, or the zip file
+    pub fn path(&self) -> PathBuf {
+        match &self.target {
+            InstanceTarget::Directory(instances_dir) => instances_dir.join(self.dir_name()),
+            InstanceTarget::Zip(path) => path.clone(),
+        }
+    }
+
+    // The name without anything that isn't allowed in a directory name on Windows.
+    // Names that are left empty, e.g. `..`, or are reserved use the default name instead, so the instance gets its own directory
+    fn dir_name(&self) -> String {
+        sanitize_dir_name(&self.name).unwrap_or_else(|| sanitize_dir_name(&Self::default_name(&self.minecraft_version)).unwrap_or_default())
+    }
+}
+
+fn sanitize_dir_name(name: &str) -> Option<String> {
+    let name: String = name.chars()
+        .map(|c| if c.is_control() || r#"<>:"/\|?*"#.contains(c) { '_' } else { c })
+        .collect();
+    let name = name.trim_start().trim_end_matches(|c: char| c == '.' || c.is_whitespace());
+
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if name.is_empty() || RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        None
+    } else {
+        Some(name.to_owned())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Pack {
+    components: Vec<PackComponent>,
+    format_version: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PackComponent {
+    uid: &'static str,
+    version: String,
+    cached_name: &'static str,
+    cached_version: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    important: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    dependency_only: bool,
+}
+
+impl PackComponent {
+    fn new(uid: &'static str, cached_name: &'static str, version: &str) -> Self {
+        PackComponent { uid, version: version.to_owned(), cached_name, cached_version: version.to_owned(), important: false, dependency_only: false }
+    }
+}
+
+/// The instances directory of Prism Launcher or PolyMC, if either is installed
+pub fn find_instances_dir() -> Option<PathBuf> {
+    let mut data_dirs = Vec::new();
+    if let Some(data_dir) = dirs::data_dir() {
+        data_dirs.extend(LAUNCHERS.iter().map(|(name, config)| (data_dir.join(name), *config)));
+    }
+    if let Some(home_dir) = dirs::home_dir() {
+        data_dirs.push((home_dir.join(PRISM_FLATPAK_DATA), LAUNCHERS[0].1));
+    }
+
+    data_dirs.into_iter().find_map(|(data_dir, config)| {
+        // The instances directory can be moved in the launcher's settings
+        let instances_dir = std::fs::read_to_string(data_dir.join(config)).ok()
+            .and_then(|config| config.lines().find_map(|line| line.strip_prefix("InstanceDir=").map(|dir| data_dir.join(dir.trim()))))
+            .unwrap_or_else(|| data_dir.join("instances"));
+        instances_dir.is_dir().then_some(instances_dir)
+    })
+}
+
+pub fn instance_conflicts(args: &InstanceInstallation) -> Vec<Conflict> {
+    let path = args.path();
+    if path.exists() {
+        vec![Conflict::Instance(path)]
+    } else {
+        Vec::new()
+    }
+}
+
+pub async fn install_instance(meta: &dyn MetaSource, args: InstanceInstallation, progress: &Progress, cancel: &CancelToken) -> Result<(), InstallError> {
+    log!("Installing instance {} in {}", args.name, args.path().display());
+
+    if let InstanceTarget::Directory(instances_dir) = &args.target {
+        if !instances_dir.exists() {
+            return Err(InstallError::DirectoryNotFound(instances_dir.clone()));
+        }
+    }
+
+    progress.set_total_steps(2);
+    progress.step(InstallStep::DownloadingProfile);
+    let response = cancel.run(meta.client_profile(&args.minecraft_version, &args.loader_version)).await?;
+    let mut profile: VersionProfile = serde_json::from_str(&response).map_err(|source| InstallError::InvalidMetaResponse {
+        what: format!("the launch json of Quilt Loader {} on Minecraft {}", &args.loader_version, &args.minecraft_version),
+        source,
+    })?;
+
+    // The launcher builds the instance from its own components, the profile only tells which mappings to use
+    apply_transformers(&args.transformers, &mut profile, &args.minecraft_version, &args.loader_version);
+
+    cancel.check()?;
+    progress.step(InstallStep::WritingFiles);
+
+    let path = args.path();
+    let pack = serde_json::to_vec_pretty(&pack(&args, &profile)).map_err(std::io::Error::from).with_path(&path)?;
+    let files = [
+        ("instance.cfg", instance_config(&args.name).into_bytes()),
+        ("mmc-pack.json", pack),
+        // Importing a zip picks this up as the instance's icon
+        (ICON_FILE, ICON.to_vec()),
+    ];
+
+    match &args.target {
+        InstanceTarget::Directory(instances_dir) => {
+            write_instance_dir(&path, &files)?;
+            add_launcher_icon(instances_dir);
+            Ok(())
+        },
+        InstanceTarget::Zip(path) => write_instance_zip(path, &files),
+    }
+}
+
+fn instance_config(name: &str) -> String {
+    // Line breaks would end the value early
+    let name = name.replace(['\r', '\n'], " ");
+    format!("[General]\nConfigVersion=1.2\nInstanceType=OneSix\niconKey={}\nname={}\n", ICON_KEY, name)
+}
+
+fn pack(args: &InstanceInstallation, profile: &VersionProfile) -> Pack {
+    let mut minecraft = PackComponent::new("net.minecraft", "Minecraft", &args.minecraft_version.version);
+    minecraft.important = true;
+    let mut components = vec![minecraft];
+
+    let mappings = profile.libraries.iter()
+        .filter_map(|library| library.coordinate().ok())
+        .find_map(|coordinate| MAPPINGS_COMPONENTS.iter()
+            .find(|(artifact, _, _)| coordinate.is_artifact(artifact))
+            .map(|(_, uid, name)| PackComponent::new(uid, name, &coordinate.version)));
+    match mappings {
+        Some(mut mappings) => {
+            mappings.dependency_only = true;
+            components.push(mappings);
+        },
+        // The launcher adds the mappings the loader depends on by itself
+        None => log!("No mappings in the Quilt profile, leaving them to the launcher"),
+    }
+
+    components.push(PackComponent::new("org.quiltmc.quilt-loader", "Quilt Loader", &args.loader_version.version));
+
+    Pack { components, format_version: 1 }
+}
+
+fn write_instance_dir(instance_dir: &Path, files: &[(&str, Vec<u8>)]) -> Result<(), InstallError> {
+    let created = !instance_dir.exists();
+    let result = std::fs::create_dir_all(instance_dir).with_path(instance_dir).and_then(|()| {
+        files.iter().try_for_each(|(name, contents)| {
+            let path = instance_dir.join(name);
+            std::fs::write(&path, contents).with_path(&path)
+        })
+    });
+
+    if result.is_err() && created {
+        let _ = std::fs::remove_dir_all(instance_dir);
+    }
+    result
+}
+
+// Instances created in place only find icons in the launcher's icons directory, next to the instances
+fn add_launcher_icon(instances_dir: &Path) {
+    let icon_path = match instances_dir.parent() {
+        Some(data_dir) if data_dir.join("icons").is_dir() => data_dir.join("icons").join(ICON_FILE),
+        _ => return,
+    };
+
+    if !icon_path.exists() {
+        if let Err(e) = std::fs::write(&icon_path, ICON) {
+            warning!("Couldn't add the Quilt icon to the launcher: {}", e);
+        }
+    }
+}
+
+fn write_instance_zip(path: &Path, files: &[(&str, Vec<u8>)]) -> Result<(), InstallError> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, contents) in files {
+        zip.start_file(*name, FileOptions::default()).map_err(InstallError::InstanceZip)?;
+        zip.write_all(contents).with_path(path)?;
+    }
+    let zip = zip.finish().map_err(InstallError::InstanceZip)?;
+
+    std::fs::write(path, zip.into_inner()).with_path(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance_path(name: &str) -> PathBuf {
+        InstanceInstallation {
+            minecraft_version: MinecraftVersion { version: String::from("1.19.2"), stable: true },
+            loader_version: LoaderVersion {
+                separator: String::from("."),
+                build: 6,
+                maven: String::from("org.quiltmc:quilt-loader:0.17.6"),
+                version: String::from("0.17.6"),
+            },
+            name: name.to_owned(),
+            target: InstanceTarget::Directory(PathBuf::from("instances")),
+            transformers: Vec::new(),
+        }.path()
+    }
+
+    #[test]
+    fn sanitizes_instance_directories() {
+        assert_eq!(instance_path("My Instance"), Path::new("instances/My Instance"));
+        assert_eq!(instance_path(" a/b: c?. "), Path::new("instances/a_b_ c_"));
+        assert_eq!(instance_path("Quilt . ."), Path::new("instances/Quilt"));
+    }
+
+    #[test]
+    fn keeps_instances_in_their_own_directory() {
+        for name in ["", " ", ".", "..", "...", "CON", "nul", "Com1.txt", "LPT9 .cfg"] {
+            assert_eq!(instance_path(name), Path::new("instances/Quilt 1.19.2"), "`{}` should use the default name", name);
+        }
+        assert_eq!(instance_path("CONSOLE"), Path::new("instances/CONSOLE"));
+    }
+}
@@ -0,0 +1,43 @@
+use std::fmt::Display;
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::Local;
+
+/// Appends to the log file, so problems can be looked at after the fact
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::log::write(format!($($arg)*))
+    };
+}
+
+/// Like `log!`, but also prints to stderr, for problems the user should know about even if the installation goes on
+macro_rules! warning {
+    ($($arg:tt)*) => {{
+        let message = format!($($arg)*);
+        eprintln!("{}", message);
+        $crate::log::write(message)
+    }};
+}
+
+pub(crate) use {log, warning};
+
+pub fn log_path() -> Option<PathBuf> {
+    let mut path = dirs::cache_dir()?;
+    path.push("quilt-installer");
+    path.push("installer.log");
+    Some(path)
+}
+
+pub fn write(message: impl Display) {
+    // Logging should never get in the way of installing, so failures here are ignored
+    let _ = (|| -> std::io::Result<()> {
+        let path = log_path().ok_or(std::io::ErrorKind::NotFound)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), message)
+    })();
+}
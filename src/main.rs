@@ -1,10 +1,28 @@
 #![windows_subsystem = "windows"]
 
-use anyhow::Result;
-use clap::{Parser, Args, Subcommand};
+use std::path::PathBuf;
 
+use anyhow::{anyhow, Result};
+use clap::{Parser, Args, Subcommand, ValueEnum};
+use selector::VersionSelector;
+
+mod cache;
+mod cancel;
+mod cli;
+mod config;
+mod error;
 mod gui;
+mod installed;
 mod installer;
+mod instance;
+mod log;
+mod maven;
+mod meta;
+mod migrate;
+mod profile;
+mod progress;
+mod selector;
+mod transform;
 pub mod theme;
 
 const FONT_REGULAR: &'static [u8] = include_bytes!("../assets/poppins/Poppins-Regular.ttf");
@@ -16,15 +34,51 @@ const ICON: &'static [u8] = include_bytes!("../assets/quilt.png");
 #[command(author, version, about)]
 pub struct Cli {
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
     /// Start the installer in no-gui mode
     #[arg(long)]
     no_gui: bool,
+    /// The quilt-meta URL to fetch versions from, or a `file://` URL to a local copy
+    #[arg(long, global = true, env = "QUILT_META_URL")]
+    meta_url: Option<String>,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
+    /// Install Quilt without opening the installer window
     Install(Install),
+    /// Remove installed Quilt versions
+    Uninstall(Uninstall),
+    /// Install Quilt for the Fabric versions in a Minecraft directory, with a Quilt copy of each Fabric profile
+    Migrate(MigrateArgs),
+    /// Show the Quilt installations in a Minecraft or server directory and whether they're up to date
+    Status {
+        /// The directory to look in
+        #[arg(long)]
+        dir: PathBuf,
+    },
+    /// List the available Minecraft or Quilt Loader versions
+    List {
+        /// Which versions to list
+        #[arg(value_enum)]
+        kind: ListKind,
+        /// Include Minecraft snapshots
+        #[arg(long)]
+        snapshots: bool,
+        /// Include Quilt Loader betas
+        #[arg(long)]
+        betas: bool,
+        /// Print the versions as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ListKind {
+    Game,
+    Loader,
 }
 
 #[derive(Args)]
@@ -35,24 +89,227 @@ struct Install {
 
 #[derive(Subcommand)]
 enum InstallCommands {
-    Client{},
-    Server{},
+    /// Install a Quilt profile for the vanilla launcher
+    Client(ClientArgs),
+    /// Install a Quilt server
+    Server(ServerArgs),
+    /// Create a Quilt instance for Prism Launcher or MultiMC
+    Instance(InstanceArgs),
+}
+
+#[derive(Args)]
+struct Uninstall {
+    #[command(subcommand)]
+    command: UninstallCommands
+}
+
+#[derive(Subcommand)]
+enum UninstallCommands {
+    /// Remove Quilt versions and their profiles from the vanilla launcher
+    Client(UninstallClientArgs),
+}
+
+#[derive(Args)]
+struct UninstallClientArgs {
+    /// The Minecraft directory to uninstall from
+    #[arg(long)]
+    dir: PathBuf,
+    /// The version to remove, e.g. `quilt-loader-0.17.6-1.19.2`, can be repeated. Lists the installed versions if none are given
+    #[arg(long = "version")]
+    versions: Vec<String>,
+    /// Remove every installed Quilt version
+    #[arg(long, conflicts_with = "versions")]
+    all: bool,
+    /// Don't list what gets removed and ask first
+    #[arg(long)]
+    yes: bool,
+}
+
+#[derive(Args)]
+struct MigrateArgs {
+    /// The Minecraft directory with the Fabric installations
+    #[arg(long)]
+    dir: PathBuf,
+    /// The Quilt Loader version to install, or a selector like `latest-stable` or `>=0.17, <0.18`
+    #[arg(long, default_value = "latest-stable")]
+    loader: VersionSelector,
+    /// Give the Quilt profiles their own game directories with a copy of the mods and config, and Quilted Fabric API instead of Fabric API
+    #[arg(long)]
+    replace_fabric_api: bool,
+    /// Replace existing installations of the same Quilt versions
+    #[arg(long)]
+    force: bool,
+    /// The Modrinth API to download Quilted Fabric API from
+    #[arg(long, env = "QUILT_MODRINTH_API_URL")]
+    modrinth_api_url: Option<String>,
+    /// Apply the named profile transformer even if it's off by default, can be repeated
+    #[arg(long = "enable-transformer")]
+    enabled_transformers: Vec<String>,
+    /// Don't apply the named profile transformer, can be repeated
+    #[arg(long = "disable-transformer")]
+    disabled_transformers: Vec<String>,
+}
+
+#[derive(Args)]
+struct ClientArgs {
+    /// The Minecraft version to install Quilt for, or `latest`, `latest-stable` or `latest-snapshot`
+    #[arg(long)]
+    minecraft: VersionSelector,
+    /// The Quilt Loader version to install, or a selector like `latest-stable` or `>=0.17, <0.18`
+    #[arg(long)]
+    loader: VersionSelector,
+    /// The Minecraft directory to install into
+    #[arg(long)]
+    dir: PathBuf,
+    /// Don't create a profile in the vanilla launcher
+    #[arg(long)]
+    no_profile: bool,
+    /// Replace an existing installation of the same versions
+    #[arg(long)]
+    force: bool,
+    /// Download the libraries as well, so the game can start without the launcher downloading them
+    #[arg(long)]
+    download_libraries: bool,
+    /// Switch the launcher profiles of older Quilt versions for the same Minecraft version to the new version
+    #[arg(long)]
+    upgrade: bool,
+    /// Delete the versions that were upgraded from
+    #[arg(long, requires = "upgrade")]
+    remove_old: bool,
+    /// Extra argument to pass to the JVM, can be repeated
+    #[arg(long = "jvm-arg", allow_hyphen_values = true)]
+    jvm_args: Vec<String>,
+    /// Apply the named profile transformer even if it's off by default, can be repeated
+    #[arg(long = "enable-transformer")]
+    enabled_transformers: Vec<String>,
+    /// Don't apply the named profile transformer, can be repeated
+    #[arg(long = "disable-transformer")]
+    disabled_transformers: Vec<String>,
+}
+
+#[derive(Args)]
+struct InstanceArgs {
+    /// The Minecraft version to install Quilt for, or `latest`, `latest-stable` or `latest-snapshot`
+    #[arg(long)]
+    minecraft: VersionSelector,
+    /// The Quilt Loader version to install, or a selector like `latest-stable` or `>=0.17, <0.18`
+    #[arg(long)]
+    loader: VersionSelector,
+    /// The name of the instance, `Quilt <minecraft version>` by default
+    #[arg(long)]
+    name: Option<String>,
+    /// The launcher's instances directory to create the instance in. Prism Launcher's is found automatically
+    #[arg(long)]
+    instances_dir: Option<PathBuf>,
+    /// Write the instance to a zip file to import in the launcher instead
+    #[arg(long, conflicts_with = "instances_dir")]
+    zip: Option<PathBuf>,
+    /// Replace an existing instance with the same name
+    #[arg(long)]
+    force: bool,
+    /// Apply the named profile transformer even if it's off by default, can be repeated
+    #[arg(long = "enable-transformer")]
+    enabled_transformers: Vec<String>,
+    /// Don't apply the named profile transformer, can be repeated
+    #[arg(long = "disable-transformer")]
+    disabled_transformers: Vec<String>,
+}
+
+#[derive(Args)]
+struct ServerArgs {
+    /// The Minecraft version to install Quilt for, or `latest`, `latest-stable` or `latest-snapshot`
+    #[arg(long)]
+    minecraft: VersionSelector,
+    /// The Quilt Loader version to install, or a selector like `latest-stable` or `>=0.17, <0.18`
+    #[arg(long)]
+    loader: VersionSelector,
+    /// The server directory to install into
+    #[arg(long)]
+    dir: PathBuf,
+    /// Download the vanilla Minecraft server jar
+    #[arg(long)]
+    download_server_jar: bool,
+    /// Generate scripts for starting the server
+    #[arg(long)]
+    generate_script: bool,
+    #[command(flatten)]
+    script: StartScriptArgs,
+    /// Create the server directory if it doesn't exist
+    #[arg(long)]
+    create_dir: bool,
+    /// Replace the files of an existing server installation
+    #[arg(long)]
+    force: bool,
+    /// The Mojang version manifest to find the server jar in, e.g. a local mirror
+    #[arg(long, env = "QUILT_VERSION_MANIFEST_URL")]
+    version_manifest_url: Option<String>,
+    /// Apply the named profile transformer even if it's off by default, can be repeated
+    #[arg(long = "enable-transformer")]
+    enabled_transformers: Vec<String>,
+    /// Don't apply the named profile transformer, can be repeated
+    #[arg(long = "disable-transformer")]
+    disabled_transformers: Vec<String>,
+}
+
+#[derive(Args)]
+struct StartScriptArgs {
+    /// The Java executable the start scripts run
+    #[arg(long, default_value = "java", requires = "generate_script")]
+    java: String,
+    /// The initial heap size of the server, e.g. `1G`
+    #[arg(long, requires = "generate_script")]
+    min_memory: Option<String>,
+    /// The maximum heap size of the server
+    #[arg(long, default_value = "2G", requires = "generate_script")]
+    max_memory: String,
+    /// Extra argument to pass to the JVM in the start scripts, can be repeated
+    #[arg(long = "jvm-arg", allow_hyphen_values = true, requires = "generate_script")]
+    jvm_args: Vec<String>,
+    /// Open the server's GUI instead of passing `nogui`
+    #[arg(long, requires = "generate_script")]
+    gui: bool,
+    /// Also generate a systemd service unit
+    #[arg(long, requires_all = ["generate_script", "systemd_user"])]
+    systemd: bool,
+    /// The user the systemd service runs the server as
+    #[arg(long, requires = "systemd")]
+    systemd_user: Option<String>,
 }
 
 fn main() -> Result<()> {
-    gui::run();
-    /*
-    let cli = Cli::parse();
-    
-    match &cli.command {
-        Commands::Install(install) => {
-            match &install.command {
-                InstallCommands::Client {  } => todo!(),
-                InstallCommands::Server {  } => todo!(),
-            }
-        },
+    // Double-clicking passes no arguments, anything else is the command line and needs the console for its output
+    if std::env::args_os().len() > 1 {
+        attach_console();
     }
-    */
 
-    Ok(())
+    let args = Cli::parse();
+    let meta = meta::from_config(args.meta_url)?;
+
+    match args.command {
+        Some(command) => cli::run(command, meta),
+        None if args.no_gui => Err(anyhow!("A command is required when running with --no-gui")),
+        None => {
+            gui::run(meta)?;
+            Ok(())
+        }
+    }
 }
+
+/// The windows subsystem starts without a console, so output would otherwise go nowhere
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // Fails when there's no parent console, e.g. when started from Explorer, and then there's nothing to attach to
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
@@ -0,0 +1,142 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// A maven artifact written as `group:artifact:version`, optionally followed by `:classifier` and `@extension`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MavenCoordinate {
+    pub group: String,
+    pub artifact: String,
+    pub version: String,
+    pub classifier: Option<String>,
+    pub extension: String,
+}
+
+#[derive(Debug, Error)]
+#[error("`{0}` isn't a maven coordinate like `group:artifact:version`")]
+pub struct InvalidCoordinate(pub String);
+
+impl MavenCoordinate {
+    /// Whether this is the given `group:artifact`, in any version
+    pub fn is_artifact(&self, artifact_id: &str) -> bool {
+        artifact_id.split_once(':') == Some((self.group.as_str(), self.artifact.as_str()))
+    }
+
+    pub fn file_name(&self) -> String {
+        match &self.classifier {
+            Some(classifier) => format!("{}-{}-{}.{}", self.artifact, self.version, classifier, self.extension),
+            None => format!("{}-{}.{}", self.artifact, self.version, self.extension),
+        }
+    }
+
+    /// The path of the artifact in a maven repository, e.g. `org/quiltmc/quilt-loader/0.17.6/quilt-loader-0.17.6.jar`
+    pub fn path(&self) -> String {
+        format!("{}/{}/{}/{}", self.group.replace('.', "/"), self.artifact, self.version, self.file_name())
+    }
+
+    pub fn url(&self, repository: &str) -> String {
+        format!("{}/{}", repository.trim_end_matches('/'), self.path())
+    }
+}
+
+impl FromStr for MavenCoordinate {
+    type Err = InvalidCoordinate;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidCoordinate(s.to_owned());
+
+        let (coordinate, extension) = match s.split_once('@') {
+            Some((coordinate, extension)) => (coordinate, extension),
+            None => (s, "jar"),
+        };
+
+        let parts: Vec<&str> = coordinate.split(':').collect();
+        if !(3..=4).contains(&parts.len()) || parts.iter().any(|part| part.is_empty()) || extension.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(MavenCoordinate {
+            group: parts[0].to_owned(),
+            artifact: parts[1].to_owned(),
+            version: parts[2].to_owned(),
+            classifier: parts.get(3).map(|classifier| classifier.to_string()),
+            extension: extension.to_owned(),
+        })
+    }
+}
+
+impl Display for MavenCoordinate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.group, self.artifact, self.version)?;
+        if let Some(classifier) = &self.classifier {
+            write!(f, ":{}", classifier)?;
+        }
+        if self.extension != "jar" {
+            write!(f, "@{}", self.extension)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_coordinates() {
+        let coordinate: MavenCoordinate = "org.quiltmc:quilt-loader:0.17.6".parse().unwrap();
+        assert_eq!(coordinate, MavenCoordinate {
+            group: String::from("org.quiltmc"),
+            artifact: String::from("quilt-loader"),
+            version: String::from("0.17.6"),
+            classifier: None,
+            extension: String::from("jar"),
+        });
+
+        let coordinate: MavenCoordinate = "org.lwjgl:lwjgl:3.3.1:natives-linux@zip".parse().unwrap();
+        assert_eq!(coordinate.classifier.as_deref(), Some("natives-linux"));
+        assert_eq!(coordinate.extension, "zip");
+    }
+
+    #[test]
+    fn rejects_invalid_coordinates() {
+        for invalid in ["", "org.quiltmc", "org.quiltmc:quilt-loader", "org.quiltmc::0.17.6", "a:b:c:d:e", "a:b:c@", "a:b:c:"] {
+            assert!(invalid.parse::<MavenCoordinate>().is_err(), "`{}` should be invalid", invalid);
+        }
+    }
+
+    #[test]
+    fn derives_paths() {
+        let coordinate: MavenCoordinate = "org.quiltmc:quilt-loader:0.17.6".parse().unwrap();
+        assert_eq!(coordinate.file_name(), "quilt-loader-0.17.6.jar");
+        assert_eq!(coordinate.path(), "org/quiltmc/quilt-loader/0.17.6/quilt-loader-0.17.6.jar");
+
+        let coordinate: MavenCoordinate = "org.lwjgl:lwjgl:3.3.1:natives-linux@zip".parse().unwrap();
+        assert_eq!(coordinate.file_name(), "lwjgl-3.3.1-natives-linux.zip");
+        assert_eq!(coordinate.path(), "org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.zip");
+    }
+
+    #[test]
+    fn derives_urls() {
+        let coordinate: MavenCoordinate = "net.fabricmc:intermediary:1.19.2".parse().unwrap();
+        let expected = "https://maven.fabricmc.net/net/fabricmc/intermediary/1.19.2/intermediary-1.19.2.jar";
+        assert_eq!(coordinate.url("https://maven.fabricmc.net/"), expected);
+        assert_eq!(coordinate.url("https://maven.fabricmc.net"), expected);
+    }
+
+    #[test]
+    fn matches_artifacts() {
+        let coordinate: MavenCoordinate = "org.quiltmc:hashed:1.19.2".parse().unwrap();
+        assert!(coordinate.is_artifact("org.quiltmc:hashed"));
+        assert!(!coordinate.is_artifact("org.quiltmc:quilt-loader"));
+        assert!(!coordinate.is_artifact("org.quiltmc"));
+    }
+
+    #[test]
+    fn displays_round_trip() {
+        for coordinate in ["org.quiltmc:quilt-loader:0.17.6", "org.lwjgl:lwjgl:3.3.1:natives-linux", "a:b:1.0@zip", "a:b:1.0:sources@zip"] {
+            assert_eq!(coordinate.parse::<MavenCoordinate>().unwrap().to_string(), coordinate);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, Utc};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+
+use crate::cache::{CachedResponse, MetaCache};
+use crate::config::Config;
+use crate::error::{InstallError, IoResultExt};
+use crate::installer::{LoaderVersion, MinecraftVersion};
+use crate::log::log;
+
+pub const DEFAULT_META_URL: &str = "https://meta.quiltmc.org/v3";
+
+#[async_trait]
+pub trait MetaSource: Debug + Send + Sync {
+    async fn minecraft_versions(&self) -> Result<Vec<MinecraftVersion>, InstallError>;
+
+    async fn loader_versions(&self) -> Result<Vec<LoaderVersion>, InstallError>;
+
+    /// The launch json for the vanilla launcher
+    async fn client_profile(&self, minecraft_version: &MinecraftVersion, loader_version: &LoaderVersion) -> Result<String, InstallError>;
+
+    async fn server_profile(&self, minecraft_version: &MinecraftVersion, loader_version: &LoaderVersion) -> Result<String, InstallError>;
+
+    /// When meta couldn't be reached and cached data was used instead, the time the oldest of that data was fetched
+    fn offline_since(&self) -> Option<DateTime<Utc>> {
+        None
+    }
+}
+
+/// Picks the meta source from, in order, the command line, the `QUILT_META_URL` environment variable
+/// (both handled by clap), the config file and finally the official quilt-meta.
+/// `file://` URLs point at a local directory instead.
+pub fn from_config(meta_url: Option<String>) -> Result<Arc<dyn MetaSource>> {
+    let meta_url = match meta_url {
+        Some(meta_url) => meta_url,
+        None => Config::load()?.meta_url.unwrap_or_else(|| DEFAULT_META_URL.to_owned()),
+    };
+
+    if meta_url.starts_with("file:") {
+        return Ok(Arc::new(LocalMetaSource::new(file_url_path(&meta_url)?)));
+    }
+
+    Ok(Arc::new(HttpMetaSource::new(meta_url)))
+}
+
+// Handles drive letters and percent-encoding, e.g. `file:///C:/My%20Mirror`
+fn file_url_path(url: &str) -> Result<PathBuf> {
+    reqwest::Url::parse(url).ok()
+        .and_then(|parsed| parsed.to_file_path().ok())
+        .ok_or_else(|| anyhow!("`{}` isn't a valid file URL", url))
+}
+
+pub fn offline_notice(since: DateTime<Utc>) -> String {
+    format!("Offline, data from {}", since.with_timezone(&Local).format("%Y-%m-%d %H:%M"))
+}
+
+#[derive(Debug)]
+pub struct HttpMetaSource {
+    base_url: String,
+    client: reqwest::Client,
+    cache: Option<MetaCache>,
+    offline_since: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl HttpMetaSource {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        HttpMetaSource {
+            base_url: base_url.into().trim_end_matches('/').to_owned(),
+            client: reqwest::Client::new(),
+            cache: MetaCache::new(),
+            offline_since: Mutex::new(None),
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<String, InstallError> {
+        let url = format!("{}/{}", self.base_url, path);
+        let cached = self.cache.as_ref().and_then(|cache| cache.load(&url));
+
+        let mut request = self.client.get(&url);
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = match (request.send().await, cached) {
+            (Ok(response), Some(cached)) if response.status() == StatusCode::NOT_MODIFIED => return Ok(cached.body),
+            (Ok(response), Some(cached)) if response.status().is_server_error() => return Ok(self.use_offline(&url, cached)),
+            (Err(_), Some(cached)) => return Ok(self.use_offline(&url, cached)),
+            (Ok(response), _) if response.status() == StatusCode::NOT_FOUND => return Err(InstallError::MetaNotFound(url)),
+            (response, _) => response.and_then(|r| r.error_for_status()).map_err(|source| InstallError::Network { url: url.clone(), source })?,
+        };
+
+        let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_owned);
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let body = response.text().await.map_err(|source| InstallError::Network { url: url.clone(), source })?;
+
+        if let Some(cache) = &self.cache {
+            let cached = CachedResponse { etag, last_modified, fetched: Utc::now(), body: body.clone() };
+            if let Err(e) = cache.store(&url, &cached) {
+                log!("Couldn't cache {}! {}", url, e);
+            }
+        }
+
+        Ok(body)
+    }
+
+    fn use_offline(&self, url: &str, cached: CachedResponse) -> String {
+        log!("Couldn't reach {}, using data from {}", url, cached.fetched);
+
+        let mut offline_since = self.offline_since.lock().unwrap();
+        *offline_since = Some(offline_since.map_or(cached.fetched, |since| since.min(cached.fetched)));
+
+        cached.body
+    }
+}
+
+#[async_trait]
+impl MetaSource for HttpMetaSource {
+    async fn minecraft_versions(&self) -> Result<Vec<MinecraftVersion>, InstallError> {
+        parse_versions("Minecraft versions", &self.get("versions/game").await?)
+    }
+
+    async fn loader_versions(&self) -> Result<Vec<LoaderVersion>, InstallError> {
+        parse_versions("Quilt Loader versions", &self.get("versions/loader").await?)
+    }
+
+    async fn client_profile(&self, minecraft_version: &MinecraftVersion, loader_version: &LoaderVersion) -> Result<String, InstallError> {
+        self.get(&format!("versions/loader/{}/{}/profile/json", minecraft_version.version, loader_version.version)).await
+            .map_err(|e| unsupported_versions(e, minecraft_version, loader_version))
+    }
+
+    async fn server_profile(&self, minecraft_version: &MinecraftVersion, loader_version: &LoaderVersion) -> Result<String, InstallError> {
+        self.get(&format!("versions/loader/{}/{}/server/json", minecraft_version.version, loader_version.version)).await
+            .map_err(|e| unsupported_versions(e, minecraft_version, loader_version))
+    }
+
+    fn offline_since(&self) -> Option<DateTime<Utc>> {
+        *self.offline_since.lock().unwrap()
+    }
+}
+
+/// Reads meta from a directory laid out like:
+/// ```text
+/// versions/game.json
+/// versions/loader.json
+/// versions/loader/<minecraft>/<loader>/profile.json
+/// versions/loader/<minecraft>/<loader>/server.json
+/// ```
+#[derive(Debug)]
+pub struct LocalMetaSource {
+    root: PathBuf,
+}
+
+impl LocalMetaSource {
+    pub fn new(root: PathBuf) -> Self {
+        LocalMetaSource { root }
+    }
+
+    fn read(&self, path: &str) -> Result<String, InstallError> {
+        let path = self.root.join(path);
+        if !path.exists() {
+            return Err(InstallError::MetaNotFound(path.display().to_string()));
+        }
+
+        std::fs::read_to_string(&path).with_path(&path)
+    }
+}
+
+#[async_trait]
+impl MetaSource for LocalMetaSource {
+    async fn minecraft_versions(&self) -> Result<Vec<MinecraftVersion>, InstallError> {
+        parse_versions("Minecraft versions", &self.read("versions/game.json")?)
+    }
+
+    async fn loader_versions(&self) -> Result<Vec<LoaderVersion>, InstallError> {
+        parse_versions("Quilt Loader versions", &self.read("versions/loader.json")?)
+    }
+
+    async fn client_profile(&self, minecraft_version: &MinecraftVersion, loader_version: &LoaderVersion) -> Result<String, InstallError> {
+        self.read(&format!("versions/loader/{}/{}/profile.json", minecraft_version.version, loader_version.version))
+            .map_err(|e| unsupported_versions(e, minecraft_version, loader_version))
+    }
+
+    async fn server_profile(&self, minecraft_version: &MinecraftVersion, loader_version: &LoaderVersion) -> Result<String, InstallError> {
+        self.read(&format!("versions/loader/{}/{}/server.json", minecraft_version.version, loader_version.version))
+            .map_err(|e| unsupported_versions(e, minecraft_version, loader_version))
+    }
+}
+
+fn parse_versions<T: DeserializeOwned>(what: &str, json: &str) -> Result<Vec<T>, InstallError> {
+    serde_json::from_str(json).map_err(|source| InstallError::InvalidMetaResponse { what: what.to_owned(), source })
+}
+
+// Meta doesn't have profiles for combinations of versions it doesn't support
+fn unsupported_versions(error: InstallError, minecraft_version: &MinecraftVersion, loader_version: &LoaderVersion) -> InstallError {
+    match error {
+        InstallError::MetaNotFound(_) => InstallError::UnsupportedVersions {
+            minecraft: minecraft_version.version.clone(),
+            loader: loader_version.version.clone(),
+        },
+        error => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn parses_file_urls() {
+        assert_eq!(file_url_path("file:///srv/meta").unwrap(), PathBuf::from("/srv/meta"));
+        assert_eq!(file_url_path("file:///srv/quilt%20meta/").unwrap(), PathBuf::from("/srv/quilt meta/"));
+        assert!(file_url_path("file://example.com/srv/meta").is_err());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn parses_file_urls() {
+        assert_eq!(file_url_path("file:///C:/mirror").unwrap(), PathBuf::from("C:\\mirror"));
+        assert_eq!(file_url_path("file:///C:/quilt%20meta").unwrap(), PathBuf::from("C:\\quilt meta"));
+    }
+
+    #[tokio::test]
+    async fn reads_local_meta() {
+        let meta = LocalMetaSource::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test-fixtures/meta"));
+
+        let minecraft_versions = meta.minecraft_versions().await.unwrap();
+        assert_eq!(minecraft_versions[0].version, "1.19.2");
+        let loader_versions = meta.loader_versions().await.unwrap();
+        assert_eq!(loader_versions[1].version, "0.17.6");
+
+        assert!(meta.client_profile(&minecraft_versions[0], &loader_versions[1]).await.is_ok());
+        assert!(matches!(
+            meta.client_profile(&minecraft_versions[1], &loader_versions[1]).await,
+            Err(InstallError::UnsupportedVersions { .. })
+        ));
+    }
+}
@@ -0,0 +1,233 @@
+use std::path::{Path, PathBuf};
+
+use crate::cancel::CancelToken;
+use crate::error::{InstallError, IoResultExt};
+use crate::installed::find_fabric_installs;
+use crate::installer::{install_client, quilt_game_dir, replace_fabric_api, ClientInstallation, LaunchProfiles, LoaderVersion, MinecraftVersion, Upgrade};
+use crate::log::{log, warning};
+use crate::meta::MetaSource;
+use crate::progress::{InstallStep, Progress};
+use crate::transform::ProfileTransformer;
+
+// Worlds, options and the rest stay with the Fabric profile, only what decides which mods load is copied
+const COPIED_GAME_FILES: [&str; 2] = ["mods", "config"];
+
+/// The Fabric versions for one Minecraft version, which a single Quilt installation replaces
+#[derive(Debug, Clone)]
+pub struct FabricMigration {
+    pub minecraft_version: String,
+    /// e.g. `fabric-loader-0.14.9-1.19.2`
+    pub fabric_versions: Vec<String>,
+    /// The launcher profiles that start one of the Fabric versions
+    pub profiles: Vec<String>,
+}
+
+impl FabricMigration {
+    /// Installs Quilt next to the Fabric versions, with a Quilt copy of every Fabric profile.
+    /// Replacing Fabric API gives the copies their own game directories, so the Fabric profiles keep theirs.
+    pub fn installation(&self, install_location: &Path, minecraft_version: MinecraftVersion, loader_version: LoaderVersion, transformers: Vec<ProfileTransformer>, replace_fabric_api: bool) -> ClientInstallation {
+        ClientInstallation {
+            minecraft_version,
+            loader_version,
+            install_location: install_location.to_owned(),
+            // Versions without a profile still get one, so the migration shows up in the launcher
+            generate_profile: self.profiles.is_empty(),
+            download_libraries: false,
+            transformers,
+            suffix: None,
+            upgrade: Some(Upgrade {
+                from: self.fabric_versions.clone(),
+                remove_old: false,
+                copy_profiles: true,
+                separate_game_dirs: replace_fabric_api,
+            }),
+        }
+    }
+
+    /// The game directories of the Fabric profiles, with the ones their Quilt copies get
+    pub fn game_dirs(&self, minecraft_dir: &Path) -> Result<Vec<(PathBuf, PathBuf)>, InstallError> {
+        let profiles_json = minecraft_dir.join("launcher_profiles.json");
+        let profiles = if profiles_json.exists() { Some(LaunchProfiles::load(&profiles_json)?) } else { None };
+
+        let mut game_dirs = Vec::new();
+        for key in &self.profiles {
+            let game_dir = match profiles.as_ref().and_then(|profiles| profiles.profiles.get(key)) {
+                Some(profile) => LaunchProfiles::game_dir(minecraft_dir, profile),
+                None => minecraft_dir.to_owned(),
+            };
+            if !game_dirs.iter().any(|(existing, _)| existing == &game_dir) {
+                let quilt_dir = quilt_game_dir(minecraft_dir, &game_dir);
+                game_dirs.push((game_dir, quilt_dir));
+            }
+        }
+
+        // The profile generated for versions without one plays in the Minecraft directory's Quilt folder
+        if game_dirs.is_empty() {
+            game_dirs.push((minecraft_dir.to_owned(), quilt_game_dir(minecraft_dir, minecraft_dir)));
+        }
+
+        Ok(game_dirs)
+    }
+}
+
+/// Installs Quilt for a migration. When the Quilt copies get their own game directories, the Fabric mods and config
+/// are copied there and Quilted Fabric API replaces Fabric API in them.
+pub async fn migrate(meta: &dyn MetaSource, migration: &FabricMigration, installation: ClientInstallation, modrinth_api_url: &str, progress: &Progress, cancel: &CancelToken) -> Result<(), InstallError> {
+    if !installation.upgrade.as_ref().is_some_and(|upgrade| upgrade.separate_game_dirs) {
+        return install_client(meta, installation, progress, cancel).await;
+    }
+
+    let game_dirs = migration.game_dirs(&installation.install_location)?;
+    let mut copied = Vec::new();
+    let result = async {
+        progress.set_total_steps(game_dirs.len());
+        for (game_dir, quilt_dir) in &game_dirs {
+            cancel.check()?;
+            progress.step(InstallStep::CopyingGameFiles(quilt_dir.clone()));
+            if !quilt_dir.exists() {
+                std::fs::create_dir_all(quilt_dir).with_path(quilt_dir)?;
+                copied.push(quilt_dir.clone());
+            }
+            for name in COPIED_GAME_FILES {
+                if game_dir.join(name).is_dir() {
+                    copy_dir(&game_dir.join(name), &quilt_dir.join(name), &mut copied)?;
+                }
+            }
+        }
+
+        let mods_dirs: Vec<PathBuf> = game_dirs.iter().map(|(_, quilt_dir)| quilt_dir.join("mods")).collect();
+        progress.reset();
+        replace_fabric_api(modrinth_api_url, &installation.minecraft_version, &mods_dirs, progress, cancel).await?;
+
+        progress.reset();
+        install_client(meta, installation, progress, cancel).await
+    }.await;
+
+    if result.is_err() {
+        log!("Migrating failed, removing the copied game files");
+        for path in copied.iter().rev() {
+            let removed = if path.is_dir() { std::fs::remove_dir_all(path) } else { std::fs::remove_file(path) };
+            if let Err(error) = removed.or_else(|error| if error.kind() == std::io::ErrorKind::NotFound { Ok(()) } else { Err(error) }) {
+                warning!("Couldn't remove {}: {}", path.display(), error);
+            }
+        }
+    }
+
+    result
+}
+
+// Files the Quilt copy already has are kept, they might be from an earlier migration
+fn copy_dir(from: &Path, to: &Path, copied: &mut Vec<PathBuf>) -> Result<(), InstallError> {
+    if !to.exists() {
+        std::fs::create_dir_all(to).with_path(to)?;
+        copied.push(to.to_owned());
+    }
+
+    for entry in std::fs::read_dir(from).with_path(from)? {
+        let entry = entry.with_path(from)?;
+        let (from, to) = (entry.path(), to.join(entry.file_name()));
+        if entry.file_type().with_path(&from)?.is_dir() {
+            copy_dir(&from, &to, copied)?;
+        } else if !to.exists() {
+            std::fs::copy(&from, &to).with_path(&to)?;
+            copied.push(to);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn find_fabric_migrations(minecraft_dir: &Path) -> Result<Vec<FabricMigration>, InstallError> {
+    let mut migrations: Vec<FabricMigration> = Vec::new();
+
+    for install in find_fabric_installs(minecraft_dir)? {
+        let minecraft_version = match install.minecraft_version {
+            Some(minecraft_version) => minecraft_version,
+            None => {
+                warning!("Can't migrate {}, its Minecraft version is unknown", install.version_id);
+                continue;
+            },
+        };
+
+        match migrations.iter_mut().find(|migration| migration.minecraft_version == minecraft_version) {
+            Some(migration) => {
+                migration.fabric_versions.push(install.version_id);
+                migration.profiles.extend(install.profiles);
+            },
+            None => migrations.push(FabricMigration {
+                minecraft_version,
+                fabric_versions: vec![install.version_id],
+                profiles: install.profiles,
+            }),
+        }
+    }
+
+    Ok(migrations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::installer::client_conflicts;
+    use crate::installer::tests::{dir_entries, fabric_api_jar, latest_versions, local_meta, maven_files, serve, serve_modrinth, temp_dir};
+    use crate::installer::Conflict;
+    use crate::transform::default_transformers;
+
+    #[tokio::test]
+    async fn migrates_fabric_profile_to_its_own_game_dir() {
+        let dir = temp_dir("migrate");
+        let meta = local_meta(&dir, &serve(|_| maven_files()));
+        let modrinth_url = serve_modrinth();
+
+        let minecraft_dir = dir.join(".minecraft");
+        let fabric_dir = minecraft_dir.join("versions/fabric-loader-0.14.9-1.19.2");
+        std::fs::create_dir_all(&fabric_dir).unwrap();
+        let fabric_profile = serde_json::json!({
+            "id": "fabric-loader-0.14.9-1.19.2",
+            "inheritsFrom": "1.19.2",
+            "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+            "libraries": [{ "name": "net.fabricmc:fabric-loader:0.14.9", "url": "https://maven.fabricmc.net/" }],
+        });
+        std::fs::write(fabric_dir.join("fabric-loader-0.14.9-1.19.2.json"), fabric_profile.to_string()).unwrap();
+
+        let pack_dir = dir.join("pack");
+        std::fs::create_dir_all(pack_dir.join("mods")).unwrap();
+        std::fs::create_dir_all(pack_dir.join("config")).unwrap();
+        fabric_api_jar(&pack_dir.join("mods/fabric-api.jar"));
+        std::fs::write(pack_dir.join("mods/sodium.jar"), b"sodium").unwrap();
+        std::fs::write(pack_dir.join("config/sodium-options.json"), b"{}").unwrap();
+
+        let profiles = serde_json::json!({
+            "profiles": {
+                "pack": { "name": "Pack", "lastVersionId": "fabric-loader-0.14.9-1.19.2", "gameDir": pack_dir },
+            },
+            "settings": {},
+            "version": 3,
+        });
+        std::fs::write(minecraft_dir.join("launcher_profiles.json"), profiles.to_string()).unwrap();
+
+        let migrations = find_fabric_migrations(&minecraft_dir).unwrap();
+        assert_eq!(migrations.len(), 1);
+        let (minecraft_version, loader_version) = latest_versions(&meta).await;
+        let installation = migrations[0].installation(&minecraft_dir, minecraft_version, loader_version, default_transformers(), true);
+        assert!(client_conflicts(&installation).is_empty());
+
+        migrate(&meta, &migrations[0], installation.clone(), &modrinth_url, &Progress::none(), &CancelToken::new()).await.unwrap();
+
+        // The Quilt copy plays with Quilted Fabric API, the Fabric profile still has Fabric API
+        let quilt_dir = dir.join("pack-quilt");
+        assert_eq!(dir_entries(&quilt_dir.join("mods")), ["fabric-api.jar.disabled", "qfapi.jar", "sodium.jar"]);
+        assert_eq!(dir_entries(&quilt_dir.join("config")), ["sodium-options.json"]);
+        assert_eq!(dir_entries(&pack_dir.join("mods")), ["fabric-api.jar", "sodium.jar"]);
+
+        let profiles = LaunchProfiles::load(&minecraft_dir.join("launcher_profiles.json")).unwrap();
+        assert_eq!(LaunchProfiles::game_dir(&minecraft_dir, &profiles.profiles["pack"]), pack_dir);
+        assert_eq!(LaunchProfiles::game_dir(&minecraft_dir, &profiles.profiles["pack-quilt"]), quilt_dir);
+        assert_eq!(LaunchProfiles::last_version_id(&profiles.profiles["pack-quilt"]), Some("quilt-loader-0.17.6-1.19.2"));
+
+        // Migrating again would replace the copy
+        assert!(client_conflicts(&installation).contains(&Conflict::LauncherProfile(String::from("pack-quilt"))));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::InstallError;
+use crate::maven::MavenCoordinate;
+
+/// A launch json as served by quilt-meta. Fields the installer doesn't care about are kept in `extra`
+/// so the profile can be written back out unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionProfile {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherits_from: Option<String>,
+    pub main_class: String,
+    /// Only present on server profiles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launcher_main_class: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Arguments>,
+    #[serde(default)]
+    pub libraries: Vec<Library>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Arguments {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game: Option<Vec<Argument>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jvm: Option<Vec<Argument>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Argument {
+    Plain(String),
+    /// An argument with rules attached, e.g. only for a certain OS
+    Conditional(Map<String, Value>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Library {
+    /// The maven coordinate, e.g. `org.quiltmc:quilt-loader:0.17.0`
+    pub name: String,
+    /// The maven repository the library is downloaded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Library {
+    pub fn coordinate(&self) -> Result<MavenCoordinate, InstallError> {
+        self.name.parse().map_err(|_| InstallError::InvalidLibrary(self.name.clone()))
+    }
+}
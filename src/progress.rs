@@ -0,0 +1,119 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tokio::sync::mpsc::UnboundedSender;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallStep {
+    DownloadingProfile,
+    DownloadingLibrary { index: usize, count: usize, name: String },
+    DownloadingServerJar,
+    WritingFiles,
+    UpdatingLauncherProfiles,
+    GeneratingScripts,
+    ReplacingFabricApi(PathBuf),
+    CopyingGameFiles(PathBuf),
+}
+
+impl Display for InstallStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallStep::DownloadingProfile => write!(f, "Downloading profile"),
+            InstallStep::DownloadingLibrary { index, count, name } => write!(f, "Downloading library {}/{}: {}", index + 1, count, name),
+            InstallStep::DownloadingServerJar => write!(f, "Downloading server jar"),
+            InstallStep::WritingFiles => write!(f, "Writing files"),
+            InstallStep::UpdatingLauncherProfiles => write!(f, "Updating launcher profiles"),
+            InstallStep::GeneratingScripts => write!(f, "Generating start scripts"),
+            InstallStep::ReplacingFabricApi(mods_dir) => write!(f, "Replacing Fabric API in {}", mods_dir.display()),
+            InstallStep::CopyingGameFiles(game_dir) => write!(f, "Copying mods and config to {}", game_dir.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressEvent {
+    pub step: InstallStep,
+    /// Progress of the whole installation, from 0 to 1
+    pub progress: f32,
+    /// Bytes downloaded in the current step, and the total if it's known
+    pub downloaded: Option<(u64, Option<u64>)>,
+}
+
+// Downloads only report every this many bytes, so listeners aren't flooded
+const DOWNLOAD_REPORT_INTERVAL: u64 = 256 * 1024;
+
+#[derive(Debug, Default)]
+struct State {
+    step: Option<InstallStep>,
+    completed: usize,
+    total: usize,
+    reported_bytes: u64,
+}
+
+/// Reports what an installation is doing. Steps are assumed to take about as long as each other.
+#[derive(Debug, Default)]
+pub struct Progress {
+    sender: Option<UnboundedSender<ProgressEvent>>,
+    state: Mutex<State>,
+}
+
+impl Progress {
+    pub fn new(sender: UnboundedSender<ProgressEvent>) -> Self {
+        Progress { sender: Some(sender), state: Mutex::default() }
+    }
+
+    /// Doesn't report anywhere
+    pub fn none() -> Self {
+        Progress::default()
+    }
+
+    pub fn set_total_steps(&self, total: usize) {
+        self.state.lock().unwrap().total = total;
+    }
+
+    /// Counts the steps from the start again, for jobs made of several installations
+    pub fn reset(&self) {
+        *self.state.lock().unwrap() = State::default();
+    }
+
+    pub fn step(&self, step: InstallStep) {
+        let mut state = self.state.lock().unwrap();
+        if state.step.is_some() {
+            state.completed += 1;
+        }
+        state.step = Some(step);
+        state.reported_bytes = 0;
+        self.send(&state, None);
+    }
+
+    pub fn download(&self, downloaded: u64, total: Option<u64>) {
+        let mut state = self.state.lock().unwrap();
+        if downloaded.saturating_sub(state.reported_bytes) < DOWNLOAD_REPORT_INTERVAL && Some(downloaded) != total {
+            return;
+        }
+
+        state.reported_bytes = downloaded;
+        self.send(&state, Some((downloaded, total)));
+    }
+
+    fn send(&self, state: &State, downloaded: Option<(u64, Option<u64>)>) {
+        let (sender, step) = match (&self.sender, &state.step) {
+            (Some(sender), Some(step)) => (sender, step),
+            _ => return,
+        };
+
+        let step_progress = match downloaded {
+            Some((downloaded, Some(total))) if total > 0 => downloaded as f32 / total as f32,
+            _ => 0.0,
+        };
+        let total = state.total.max(state.completed + 1);
+
+        // The receiving end going away just means nobody's listening anymore
+        let _ = sender.send(ProgressEvent {
+            step: step.clone(),
+            progress: ((state.completed as f32 + step_progress) / total as f32).min(1.0),
+            downloaded,
+        });
+    }
+}
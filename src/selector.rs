@@ -0,0 +1,169 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use semver::{Version, VersionReq};
+
+use crate::installer::{LoaderVersion, MinecraftVersion};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionSelector {
+    Latest,
+    LatestStable,
+    LatestSnapshot,
+    Exact(String),
+    Range(VersionReq),
+}
+
+impl FromStr for VersionSelector {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        match s {
+            "" => Err(anyhow!("Version can't be empty")),
+            "latest" => Ok(Self::Latest),
+            "latest-stable" => Ok(Self::LatestStable),
+            "latest-snapshot" => Ok(Self::LatestSnapshot),
+            // Plain versions would otherwise parse as caret requirements, so only operators make a range
+            _ if s.contains(['<', '>', '=', '~', '^', '*', ',']) => {
+                VersionReq::parse(s)
+                    .map(Self::Range)
+                    .map_err(|e| anyhow!("Invalid version range `{}`: {}", s, e))
+            },
+            _ => Ok(Self::Exact(s.to_owned())),
+        }
+    }
+}
+
+impl Display for VersionSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Latest => write!(f, "latest"),
+            Self::LatestStable => write!(f, "latest-stable"),
+            Self::LatestSnapshot => write!(f, "latest-snapshot"),
+            Self::Exact(version) => write!(f, "{}", version),
+            Self::Range(range) => write!(f, "{}", range),
+        }
+    }
+}
+
+impl VersionSelector {
+    // Versions are expected in quilt-meta's order, newest first
+    pub fn select_minecraft<'a>(&self, versions: &'a [MinecraftVersion]) -> Option<&'a MinecraftVersion> {
+        match self {
+            Self::Latest => versions.first(),
+            Self::LatestStable => versions.iter().find(|v| v.stable),
+            Self::LatestSnapshot => versions.iter().find(|v| !v.stable),
+            Self::Exact(version) => versions.iter().find(|v| &v.version == version),
+            Self::Range(range) => select_range(versions.iter(), range, |v| &v.version),
+        }
+    }
+
+    pub fn select_loader<'a>(&self, versions: &'a [LoaderVersion]) -> Option<&'a LoaderVersion> {
+        match self {
+            Self::Latest => versions.first(),
+            Self::LatestStable => versions.iter().find(|v| !v.is_beta()),
+            Self::LatestSnapshot => versions.iter().find(|v| v.is_beta()),
+            Self::Exact(version) => versions.iter().find(|v| &v.version == version),
+            Self::Range(range) => select_range(versions.iter(), range, |v| &v.version),
+        }
+    }
+}
+
+fn select_range<'a, T>(versions: impl Iterator<Item = &'a T>, range: &VersionReq, version: impl Fn(&T) -> &str) -> Option<&'a T> {
+    versions
+        .filter_map(|v| parse_lenient(version(v)).map(|parsed| (parsed, v)))
+        .filter(|(parsed, _)| range.matches(parsed))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, v)| v)
+}
+
+// Minecraft releases like `1.19` aren't valid semver, so pad them out to `1.19.0`
+pub fn parse_lenient(version: &str) -> Option<Version> {
+    Version::parse(version)
+        .or_else(|_| Version::parse(&format!("{}.0", version)))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minecraft(versions: &[(&str, bool)]) -> Vec<MinecraftVersion> {
+        versions.iter().map(|(version, stable)| MinecraftVersion { version: version.to_string(), stable: *stable }).collect()
+    }
+
+    fn loader(versions: &[&str]) -> Vec<LoaderVersion> {
+        versions.iter().map(|version| LoaderVersion {
+            separator: String::from("."),
+            build: 0,
+            maven: format!("org.quiltmc:quilt-loader:{}", version),
+            version: version.to_string(),
+        }).collect()
+    }
+
+    #[test]
+    fn parses_keywords_and_exact_versions() {
+        assert_eq!("latest".parse::<VersionSelector>().unwrap(), VersionSelector::Latest);
+        assert_eq!(" latest-stable ".parse::<VersionSelector>().unwrap(), VersionSelector::LatestStable);
+        assert_eq!("latest-snapshot".parse::<VersionSelector>().unwrap(), VersionSelector::LatestSnapshot);
+        assert_eq!("1.19.2".parse::<VersionSelector>().unwrap(), VersionSelector::Exact(String::from("1.19.2")));
+        assert_eq!("22w42a".parse::<VersionSelector>().unwrap(), VersionSelector::Exact(String::from("22w42a")));
+        assert!("".parse::<VersionSelector>().is_err());
+        assert!("  ".parse::<VersionSelector>().is_err());
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(">=0.17, <0.18".parse::<VersionSelector>().unwrap(), VersionSelector::Range(VersionReq::parse(">=0.17, <0.18").unwrap()));
+        assert!(matches!("~0.17".parse::<VersionSelector>().unwrap(), VersionSelector::Range(_)));
+        assert!(matches!("0.17.*".parse::<VersionSelector>().unwrap(), VersionSelector::Range(_)));
+        assert!(">=banana".parse::<VersionSelector>().is_err());
+    }
+
+    #[test]
+    fn displays_what_was_parsed() {
+        for selector in ["latest", "latest-stable", "latest-snapshot", "1.19.2", ">=0.17, <0.18"] {
+            assert_eq!(selector.parse::<VersionSelector>().unwrap().to_string(), selector);
+        }
+    }
+
+    #[test]
+    fn selects_minecraft_versions() {
+        let versions = minecraft(&[("22w42a", false), ("1.19.2", true), ("1.19", true), ("1.18.2", true)]);
+        let select = |selector: &str| selector.parse::<VersionSelector>().unwrap().select_minecraft(&versions).map(|v| v.version.as_str());
+
+        assert_eq!(select("latest"), Some("22w42a"));
+        assert_eq!(select("latest-stable"), Some("1.19.2"));
+        assert_eq!(select("latest-snapshot"), Some("22w42a"));
+        assert_eq!(select("1.18.2"), Some("1.18.2"));
+        assert_eq!(select("1.17"), None);
+        // `1.19` is padded to `1.19.0`, snapshots don't parse and are never in a range
+        assert_eq!(select("<1.19.1"), Some("1.19"));
+        assert_eq!(select(">=1.18, <1.19"), Some("1.18.2"));
+        assert_eq!(select(">=1.20"), None);
+    }
+
+    #[test]
+    fn selects_loader_versions() {
+        let versions = loader(&["0.18.1-beta.2", "0.17.6", "0.17.10", "0.17.5-beta.1", "0.16.1"]);
+        let select = |selector: &str| selector.parse::<VersionSelector>().unwrap().select_loader(&versions).map(|v| v.version.as_str());
+
+        assert_eq!(select("latest"), Some("0.18.1-beta.2"));
+        assert_eq!(select("latest-stable"), Some("0.17.6"));
+        assert_eq!(select("latest-snapshot"), Some("0.18.1-beta.2"));
+        // Ranges compare versions instead of taking meta's order
+        assert_eq!(select(">=0.17, <0.18"), Some("0.17.10"));
+        assert_eq!(select("<0.17"), Some("0.16.1"));
+        assert_eq!(select(">=0.19"), None);
+    }
+
+    #[test]
+    fn parses_versions_leniently() {
+        assert_eq!(parse_lenient("1.19"), Some(Version::new(1, 19, 0)));
+        assert_eq!(parse_lenient("1.19.2"), Some(Version::new(1, 19, 2)));
+        assert_eq!(parse_lenient("22w42a"), None);
+    }
+}
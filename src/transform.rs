@@ -0,0 +1,257 @@
+use anyhow::{anyhow, Result};
+use semver::{Prerelease, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::installer::{LoaderVersion, MinecraftVersion};
+use crate::log::log;
+use crate::profile::{Argument, Library, VersionProfile};
+use crate::selector::parse_lenient;
+
+/// A post-processing step applied to the profile from quilt-meta before it's installed.
+/// Transformers can be configured in the config file, e.g.
+/// ```json
+/// { "transformers": [{ "name": "mappings", "mappings": "intermediary", "loader": "<0.18" }] }
+/// ```
+/// Transformers with `"enabled": false` are only applied to installs that turn them on.
+/// Pre-releases count as the release they lead up to. Snapshots like `22w45a` don't fit in a range,
+/// so transformers with a Minecraft range skip them unless they set `"snapshots": true`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileTransformer {
+    #[serde(flatten)]
+    pub transform: Transform,
+    /// Only apply to Minecraft versions in this range
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minecraft: Option<VersionReq>,
+    /// Apply to snapshots too when there's a Minecraft range
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub snapshots: bool,
+    /// Only apply to Quilt Loader versions in this range
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader: Option<VersionReq>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "name", rename_all = "kebab-case")]
+pub enum Transform {
+    /// Keeps only one set of mappings in the profile
+    Mappings { mappings: Mappings },
+    /// Adds extra arguments for the JVM
+    JvmArgs { args: Vec<String> },
+    /// Replaces or removes libraries
+    LibraryOverrides { overrides: Vec<LibraryOverride> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mappings {
+    Intermediary,
+    Hashed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryOverride {
+    /// The `group:artifact` of the library to override
+    pub artifact: String,
+    /// The replacement maven coordinate, the library is removed if there's none
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+impl ProfileTransformer {
+    pub fn new(transform: Transform) -> Self {
+        ProfileTransformer { transform, minecraft: None, snapshots: false, loader: None, enabled: true }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.transform {
+            Transform::Mappings { .. } => "mappings",
+            Transform::JvmArgs { .. } => "jvm-args",
+            Transform::LibraryOverrides { .. } => "library-overrides",
+        }
+    }
+
+    /// What the transformer does, for choosing which ones to apply
+    pub fn description(&self) -> String {
+        let mut description = match &self.transform {
+            Transform::Mappings { mappings: Mappings::Intermediary } => String::from("Only use intermediary mappings"),
+            Transform::Mappings { mappings: Mappings::Hashed } => String::from("Only use hashed mappings"),
+            Transform::JvmArgs { args } => format!("Add JVM arguments {}", args.join(" ")),
+            Transform::LibraryOverrides { overrides } => {
+                let artifacts: Vec<&str> = overrides.iter().map(|library_override| library_override.artifact.as_str()).collect();
+                format!("Override {}", artifacts.join(", "))
+            },
+        };
+        if let Some(minecraft) = &self.minecraft {
+            description.push_str(&format!(" on Minecraft {}", minecraft));
+            if self.snapshots {
+                description.push_str(" and snapshots");
+            }
+        }
+        if let Some(loader) = &self.loader {
+            description.push_str(&format!(" with Quilt Loader {}", loader));
+        }
+        description
+    }
+
+    pub fn applies_to(&self, minecraft_version: &MinecraftVersion, loader_version: &LoaderVersion) -> bool {
+        let applies_to_minecraft = match (&self.minecraft, parse_lenient(&minecraft_version.version)) {
+            (None, _) => true,
+            // A range only matches pre-releases of its own versions, `1.19.3-pre1` should count as `1.19.3`
+            (Some(range), Some(mut version)) => {
+                version.pre = Prerelease::EMPTY;
+                range.matches(&version)
+            },
+            (Some(_), None) => {
+                if !self.snapshots {
+                    log!("Skipping the {} transformer for snapshot {}, it only applies to Minecraft {}", self.name(), minecraft_version, self.minecraft.as_ref().unwrap());
+                }
+                self.snapshots
+            },
+        };
+
+        let applies_to_loader = match &self.loader {
+            Some(range) => parse_lenient(&loader_version.version).is_some_and(|version| range.matches(&version)),
+            None => true,
+        };
+
+        applies_to_minecraft && applies_to_loader
+    }
+
+    pub fn apply(&self, profile: &mut VersionProfile) {
+        match &self.transform {
+            Transform::Mappings { mappings } => {
+                let other = match mappings {
+                    Mappings::Intermediary => "org.quiltmc:hashed",
+                    Mappings::Hashed => "net.fabricmc:intermediary",
+                };
+                profile.libraries.retain(|lib| !is_artifact(lib, other));
+            },
+            Transform::JvmArgs { args } => {
+                profile.arguments.get_or_insert_with(Default::default)
+                    .jvm.get_or_insert_with(Vec::new)
+                    .extend(args.iter().cloned().map(Argument::Plain));
+            },
+            Transform::LibraryOverrides { overrides } => {
+                for library_override in overrides {
+                    profile.libraries.retain(|lib| !is_artifact(lib, &library_override.artifact));
+
+                    if let Some(name) = &library_override.name {
+                        profile.libraries.push(Library {
+                            name: name.clone(),
+                            url: library_override.url.clone(),
+                            extra: Default::default(),
+                        });
+                    }
+                }
+            },
+        }
+    }
+}
+
+// Libraries that aren't valid coordinates are left alone and reported when they're downloaded
+fn is_artifact(library: &Library, artifact_id: &str) -> bool {
+    library.coordinate().is_ok_and(|coordinate| coordinate.is_artifact(artifact_id))
+}
+
+pub fn default_transformers() -> Vec<ProfileTransformer> {
+    vec![
+        // Quilt-meta specifies both hashed and intermediary, but providing both to quilt-loader causes it to silently fail remapping.
+        // This really shouldn't be fixed here in the installer, but we need a solution now.
+        // Once loader handles it, gate this on the loader version in the config file.
+        ProfileTransformer::new(Transform::Mappings { mappings: Mappings::Intermediary }),
+    ]
+}
+
+/// The transformers from the config file, or the defaults if it doesn't list any
+pub fn configured_transformers() -> Result<Vec<ProfileTransformer>> {
+    Ok(Config::load()?.transformers.unwrap_or_else(default_transformers))
+}
+
+/// Turns the named transformers on or off for a single install, turning off wins
+pub fn toggle_transformers(transformers: &mut [ProfileTransformer], enabled: &[String], disabled: &[String]) -> Result<()> {
+    for name in enabled.iter().chain(disabled) {
+        if !transformers.iter().any(|t| t.name() == name) {
+            return Err(anyhow!("There's no profile transformer named `{}`", name));
+        }
+    }
+
+    for transformer in transformers {
+        if enabled.iter().any(|name| name == transformer.name()) {
+            transformer.enabled = true;
+        }
+        if disabled.iter().any(|name| name == transformer.name()) {
+            transformer.enabled = false;
+        }
+    }
+    Ok(())
+}
+
+pub fn apply_transformers(transformers: &[ProfileTransformer], profile: &mut VersionProfile, minecraft_version: &MinecraftVersion, loader_version: &LoaderVersion) {
+    for transformer in transformers.iter().filter(|t| t.enabled && t.applies_to(minecraft_version, loader_version)) {
+        transformer.apply(profile);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configured(json: &str) -> Vec<ProfileTransformer> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn transformers_are_enabled_unless_configured_otherwise() {
+        let transformers = configured(r#"[
+            { "name": "mappings", "mappings": "intermediary", "loader": "<0.18" },
+            { "name": "jvm-args", "args": ["-Dfoo=bar"], "enabled": false }
+        ]"#);
+        assert!(transformers[0].enabled);
+        assert!(!transformers[1].enabled);
+        assert_eq!(transformers[0].description(), "Only use intermediary mappings with Quilt Loader <0.18");
+        assert_eq!(transformers[1].description(), "Add JVM arguments -Dfoo=bar");
+    }
+
+    #[test]
+    fn toggles_transformers_by_name() {
+        let mut transformers = configured(r#"[
+            { "name": "mappings", "mappings": "intermediary" },
+            { "name": "jvm-args", "args": ["-Dfoo=bar"], "enabled": false }
+        ]"#);
+
+        toggle_transformers(&mut transformers, &[String::from("jvm-args")], &[String::from("mappings")]).unwrap();
+        assert!(!transformers[0].enabled);
+        assert!(transformers[1].enabled);
+
+        // Turning off wins
+        toggle_transformers(&mut transformers, &[String::from("jvm-args")], &[String::from("jvm-args")]).unwrap();
+        assert!(!transformers[1].enabled);
+
+        assert!(toggle_transformers(&mut transformers, &[String::from("mapings")], &[]).is_err());
+    }
+
+    #[test]
+    fn matches_minecraft_ranges_against_snapshots() {
+        let transformers = configured(r#"[
+            { "name": "mappings", "mappings": "intermediary", "minecraft": ">=1.19" },
+            { "name": "jvm-args", "args": ["-Dfoo=bar"], "minecraft": ">=1.19", "snapshots": true }
+        ]"#);
+        let minecraft = |version: &str| MinecraftVersion { version: version.to_owned(), stable: false };
+        let loader = LoaderVersion { separator: String::from("."), build: 0, maven: String::from("org.quiltmc:quilt-loader:0.17.6"), version: String::from("0.17.6") };
+
+        assert!(transformers[0].applies_to(&minecraft("1.19.3-pre1"), &loader));
+        assert!(!transformers[0].applies_to(&minecraft("1.18.2-rc1"), &loader));
+        assert!(!transformers[0].applies_to(&minecraft("22w45a"), &loader));
+        assert!(transformers[1].applies_to(&minecraft("22w45a"), &loader));
+        assert_eq!(transformers[1].description(), "Add JVM arguments -Dfoo=bar on Minecraft >=1.19 and snapshots");
+    }
+}